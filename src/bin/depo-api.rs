@@ -0,0 +1,482 @@
+//! `depo-api`: builds depo requests from arguments and decodes requests,
+//! responses and other depo URs, without contacting a depo.
+//!
+//! ```text
+//! depo-api request store-share --file share.bin --label "Group 1"
+//! depo-api request get-shares --receipt ur:depo-receipt/...
+//! depo-api request --key user.key --depo depo.xid get-recovery
+//! depo-api decode ur:depo-request/...
+//! depo-api decode --key depo.key ur:envelope/...
+//! depo-api decode --key user.key --function getShares ur:envelope/...
+//! ```
+//!
+//! Key files hold a `ur:crypto-prvkey-base` and XID document files hold the
+//! document as a `ur:envelope`, each optionally followed by whitespace.
+
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use bc_components::{
+    ARID, PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider, XIDProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use clap::{Args, Parser, Subcommand};
+use depo_api::{
+    DELETE_ACCOUNT_FUNCTION_NAME, DELETE_SHARES_FUNCTION_NAME, DeleteAccount,
+    DeleteShares, DepoRequest, FINISH_RECOVERY_FUNCTION_NAME, FinishRecovery,
+    GET_RECOVERY_FUNCTION_NAME, GET_SHARES_FUNCTION_NAME,
+    GET_XID_DOCUMENT_FUNCTION_NAME, GetRecovery, GetRecoveryResult, GetShares,
+    GetSharesResult, GetXIDDocument, GetXIDDocumentResult,
+    LIST_SHARES_FUNCTION_NAME, ListShares, ListSharesResult, OkResult, Receipt,
+    RecoveryMethod, START_RECOVERY_FUNCTION_NAME, STORE_SHARE_FUNCTION_NAME,
+    STORE_SHARES_FUNCTION_NAME, SealedRecoveryManifest, ShareMetadata,
+    StartRecovery, StartRecoveryResult, StoreShare, StoreShareResult,
+    StoreShares, StoreSharesResult, UPDATE_RECOVERY_FUNCTION_NAME,
+    UPDATE_XID_DOCUMENT_FUNCTION_NAME, UpdateRecovery, UpdateXIDDocument,
+    client::REQUEST_LIFETIME,
+    ur::{
+        TAG_NAME_DEPO_GET_RECOVERY_RESULT, TAG_NAME_DEPO_GET_SHARES_RESULT,
+        TAG_NAME_DEPO_GET_XID_DOCUMENT_RESULT,
+        TAG_NAME_DEPO_LIST_SHARES_RESULT, TAG_NAME_DEPO_OK_RESULT,
+        TAG_NAME_DEPO_RECEIPT, TAG_NAME_DEPO_RECOVERY_MANIFEST,
+        TAG_NAME_DEPO_START_RECOVERY_RESULT, TAG_NAME_DEPO_STORE_SHARE_RESULT,
+        TAG_NAME_DEPO_STORE_SHARES_RESULT,
+    },
+};
+use gstp::prelude::*;
+
+type CliResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(version, about = "Build and inspect depo requests offline")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build a request, printed as a `ur:depo-request`, or as a sealed
+    /// `ur:envelope` if `--key` and `--depo` are given.
+    Request(RequestArgs),
+    /// Decode a depo UR or a sealed request or response and print it.
+    Decode(DecodeArgs),
+}
+
+#[derive(Args)]
+struct RequestArgs {
+    /// File holding the sender's private key base.
+    #[arg(long, requires = "depo")]
+    key: Option<PathBuf>,
+    /// File holding the depo's XID document, to seal the request to.
+    #[arg(long, requires = "key")]
+    depo: Option<PathBuf>,
+    #[command(subcommand)]
+    request: RequestCommand,
+}
+
+#[derive(Subcommand)]
+enum RequestCommand {
+    StoreShare {
+        /// File holding the share data.
+        #[arg(long)]
+        file: PathBuf,
+        #[command(flatten)]
+        metadata: MetadataArgs,
+        /// Compress the share data in the request.
+        #[arg(long)]
+        compress: bool,
+    },
+    StoreShares {
+        /// Files holding the share data, one share per file.
+        #[arg(long = "file", required = true)]
+        files: Vec<PathBuf>,
+        /// Compress the share data in the request.
+        #[arg(long)]
+        compress: bool,
+    },
+    GetShares {
+        /// Receipts of the shares to get; all shares if omitted.
+        #[arg(long = "receipt")]
+        receipts: Vec<String>,
+    },
+    ListShares {
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Cursor from a previous page, as hex.
+        #[arg(long)]
+        cursor: Option<String>,
+    },
+    DeleteShares {
+        /// Receipts of the shares to delete.
+        #[arg(long = "receipt", required = true)]
+        receipts: Vec<String>,
+    },
+    UpdateRecovery {
+        /// An email address, a `mailto:` or `tel:` URI, another URI, or a
+        /// recovery code; clears the recovery method if omitted.
+        #[arg(long)]
+        recovery: Option<String>,
+    },
+    GetRecovery,
+    StartRecovery {
+        /// An email address, a `mailto:` or `tel:` URI, another URI, or a
+        /// recovery code.
+        #[arg(long)]
+        recovery: String,
+    },
+    FinishRecovery {
+        /// The continuation returned by `startRecovery`, as a `ur:envelope`.
+        #[arg(long)]
+        continuation: String,
+    },
+    GetXidDocument,
+    UpdateXidDocument {
+        /// File holding the new XID document.
+        #[arg(long)]
+        file: PathBuf,
+        /// File holding the XID document the update replaces, so it is
+        /// refused if the depo holds another.
+        #[arg(long)]
+        previous: Option<PathBuf>,
+        /// File holding the private key base of a key of the account's
+        /// current XID document, to sign a proof that it approves the update.
+        #[arg(long)]
+        proof_key: Option<PathBuf>,
+    },
+    DeleteAccount,
+}
+
+#[derive(Args)]
+struct MetadataArgs {
+    #[arg(long)]
+    label: Option<String>,
+    #[arg(long)]
+    content_type: Option<String>,
+    #[arg(long)]
+    application_tag: Option<String>,
+}
+
+#[derive(Args)]
+struct DecodeArgs {
+    /// The UR to decode.
+    ur: String,
+    /// File holding the recipient's private key base, to open sealed
+    /// requests and responses.
+    #[arg(long)]
+    key: Option<PathBuf>,
+    /// The function a sealed response answers, to decode its result.
+    #[arg(long)]
+    function: Option<String>,
+}
+
+fn main() -> ExitCode {
+    depo_api::register_tags();
+
+    match run(Cli::parse()) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<String> {
+    match cli.command {
+        Command::Request(args) => request(args),
+        Command::Decode(args) => decode(args),
+    }
+}
+
+//
+// Building requests
+//
+
+fn request(args: RequestArgs) -> CliResult<String> {
+    let request = build_request(args.request)?;
+    match (args.key, args.depo) {
+        (Some(key), Some(depo)) => seal_request(
+            request,
+            &read_private_key_base(&key)?,
+            read_xid_document(&depo)?,
+        ),
+        _ => Ok(request.ur_string()),
+    }
+}
+
+fn build_request(command: RequestCommand) -> CliResult<DepoRequest> {
+    let request = match command {
+        RequestCommand::StoreShare { file, metadata, compress } => {
+            let request = StoreShare::new_with_metadata(
+                fs::read(file)?,
+                share_metadata(metadata),
+            );
+            compressed(request, compress).into()
+        }
+        RequestCommand::StoreShares { files, compress } => {
+            let shares = files
+                .into_iter()
+                .map(|file| {
+                    let request = StoreShare::new(fs::read(file)?);
+                    Ok(compressed(request, compress))
+                })
+                .collect::<CliResult<Vec<_>>>()?;
+            StoreShares::new(shares).into()
+        }
+        RequestCommand::GetShares { receipts } => {
+            GetShares::new(parse_receipts(&receipts)?).into()
+        }
+        RequestCommand::ListShares { limit, cursor } => {
+            let mut request = ListShares::new();
+            if let Some(limit) = limit {
+                request = request.with_limit(limit);
+            }
+            if let Some(cursor) = cursor {
+                request = request.with_cursor(hex::decode(cursor)?);
+            }
+            request.into()
+        }
+        RequestCommand::DeleteShares { receipts } => {
+            DeleteShares::new(parse_receipts(&receipts)?).into()
+        }
+        RequestCommand::UpdateRecovery { recovery } => {
+            let recovery =
+                recovery.map(|s| RecoveryMethod::parse(&s)).transpose()?;
+            UpdateRecovery::new(recovery).into()
+        }
+        RequestCommand::GetRecovery => GetRecovery::new().into(),
+        RequestCommand::StartRecovery { recovery } => {
+            StartRecovery::new(RecoveryMethod::parse(&recovery)?).into()
+        }
+        RequestCommand::FinishRecovery { continuation } => {
+            FinishRecovery::new(Envelope::from_ur_string(continuation)?).into()
+        }
+        RequestCommand::GetXidDocument => GetXIDDocument::new().into(),
+        RequestCommand::UpdateXidDocument { file, previous, proof_key } => {
+            let new_xid_document = read_xid_document(&file)?;
+            let account = new_xid_document.xid();
+            let mut request = UpdateXIDDocument::new(new_xid_document);
+            if let Some(previous) = previous {
+                request = request
+                    .with_previous_xid_document(&read_xid_document(&previous)?);
+            }
+            if let Some(proof_key) = proof_key {
+                let keys = read_private_key_base(&proof_key)?.private_keys();
+                request = request.with_proof(&account, &keys)?;
+            }
+            request.into()
+        }
+        RequestCommand::DeleteAccount => DeleteAccount::new().into(),
+    };
+    Ok(request)
+}
+
+fn compressed(request: StoreShare, compress: bool) -> StoreShare {
+    if compress { request.with_compression() } else { request }
+}
+
+fn share_metadata(args: MetadataArgs) -> ShareMetadata {
+    let mut metadata = ShareMetadata::new();
+    if let Some(label) = args.label {
+        metadata = metadata.with_label(label);
+    }
+    if let Some(content_type) = args.content_type {
+        metadata = metadata.with_content_type(content_type);
+    }
+    if let Some(application_tag) = args.application_tag {
+        metadata = metadata.with_application_tag(application_tag);
+    }
+    metadata
+}
+
+fn parse_receipts(receipts: &[String]) -> CliResult<Vec<Receipt>> {
+    Ok(receipts
+        .iter()
+        .map(Receipt::from_ur_string)
+        .collect::<dcbor::Result<_>>()?)
+}
+
+/// Seals `request` from the holder of `private_key_base` to the depo, as
+/// `DepoClient` does.
+fn seal_request(
+    request: DepoRequest,
+    private_key_base: &PrivateKeyBase,
+    depo_xid_document: XIDDocument,
+) -> CliResult<String> {
+    let sender: XIDDocument = private_key_base.public_keys().into();
+    let now = Date::now();
+    let valid_until = Date::from_timestamp(now.timestamp() + REQUEST_LIFETIME);
+    let sealed_request =
+        SealedRequest::new_with_body(request.into(), ARID::new(), sender)
+            .with_date(now);
+    let envelope = sealed_request.to_envelope(
+        Some(valid_until),
+        Some(&private_key_base.private_keys()),
+        Some(&depo_xid_document),
+    )?;
+    Ok(envelope.ur_string())
+}
+
+fn read_ur_file(path: &PathBuf) -> CliResult<String> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+fn read_private_key_base(path: &PathBuf) -> CliResult<PrivateKeyBase> {
+    Ok(PrivateKeyBase::from_ur_string(read_ur_file(path)?)?)
+}
+
+fn read_xid_document(path: &PathBuf) -> CliResult<XIDDocument> {
+    let envelope = Envelope::from_ur_string(read_ur_file(path)?)?;
+    Ok(XIDDocument::try_from(envelope)?)
+}
+
+//
+// Decoding
+//
+
+fn decode(args: DecodeArgs) -> CliResult<String> {
+    let ur = UR::from_ur_string(args.ur.trim())?;
+    if ur.ur_type_str() != "envelope" {
+        return describe_depo_ur(&ur);
+    }
+    let envelope = Envelope::from_ur(&ur)?;
+    let Some(key) = args.key else {
+        return Ok(envelope.format());
+    };
+    let private_keys = read_private_key_base(&key)?.private_keys();
+    if let Ok(request) =
+        SealedRequest::try_from_envelope(&envelope, None, None, &private_keys)
+    {
+        return describe_request(&request);
+    }
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        None,
+        None,
+        &private_keys,
+    )?;
+    describe_response(&response, args.function.as_deref())
+}
+
+fn describe(display: impl std::fmt::Display, envelope: &Envelope) -> String {
+    format!("{}\n\n{}", display, envelope.format())
+}
+
+fn describe_request(request: &SealedRequest) -> CliResult<String> {
+    let body = request.body().clone();
+    let envelope = body.to_envelope();
+    let request_description = DepoRequest::try_from(body)?.to_string();
+    Ok(format!(
+        "request {} from {}\n{}",
+        request.id(),
+        request.sender().xid(),
+        describe(request_description, &envelope)
+    ))
+}
+
+fn describe_response(
+    response: &SealedResponse,
+    function: Option<&str>,
+) -> CliResult<String> {
+    let heading = format!(
+        "response {} from {}",
+        response
+            .id()
+            .map_or_else(|| "without ID".to_string(), |id| id.to_string()),
+        response.sender().xid()
+    );
+    if response.is_err() {
+        let error = response.error()?.format();
+        return Ok(format!("{} (error)\n{}", heading, error));
+    }
+    let result = response.result()?.clone();
+    let description = match function {
+        Some(function) => describe_result(function, result.clone())?,
+        None => "(pass --function to decode the result)".to_string(),
+    };
+    Ok(format!("{}\n{}", heading, describe(description, &result)))
+}
+
+/// Decodes a sealed response's result as the result of `function`.
+fn describe_result(function: &str, result: Envelope) -> CliResult<String> {
+    let description = match function {
+        GET_RECOVERY_FUNCTION_NAME => {
+            GetRecoveryResult::try_from(result)?.to_string()
+        }
+        GET_SHARES_FUNCTION_NAME => {
+            GetSharesResult::try_from(result)?.to_string()
+        }
+        GET_XID_DOCUMENT_FUNCTION_NAME => {
+            GetXIDDocumentResult::try_from(result)?.to_string()
+        }
+        LIST_SHARES_FUNCTION_NAME => {
+            ListSharesResult::try_from(result)?.to_string()
+        }
+        START_RECOVERY_FUNCTION_NAME => {
+            StartRecoveryResult::try_from(result)?.to_string()
+        }
+        STORE_SHARE_FUNCTION_NAME => {
+            StoreShareResult::try_from(result)?.to_string()
+        }
+        STORE_SHARES_FUNCTION_NAME => {
+            StoreSharesResult::try_from(result)?.to_string()
+        }
+        DELETE_ACCOUNT_FUNCTION_NAME
+        | DELETE_SHARES_FUNCTION_NAME
+        | FINISH_RECOVERY_FUNCTION_NAME
+        | UPDATE_RECOVERY_FUNCTION_NAME
+        | UPDATE_XID_DOCUMENT_FUNCTION_NAME => {
+            OkResult::try_from(result)?.to_string()
+        }
+        _ => return Err(format!("unknown function: {}", function).into()),
+    };
+    Ok(description)
+}
+
+/// Decodes a UR of one of the crate's own types.
+fn describe_depo_ur(ur: &UR) -> CliResult<String> {
+    fn result<T>(ur: &UR) -> CliResult<String>
+    where
+        T: URDecodable + std::fmt::Display,
+    {
+        let value = T::from_ur(ur)?;
+        Ok(describe(&value, &Envelope::from_untagged_cbor(ur.cbor())?))
+    }
+
+    match ur.ur_type_str() {
+        TAG_NAME_DEPO_RECEIPT => {
+            let receipt = Receipt::from_ur(ur)?;
+            let envelope = Envelope::from_untagged_cbor(ur.cbor())?;
+            Ok(describe(format!("{:?}", receipt), &envelope))
+        }
+        TAG_NAME_DEPO_RECOVERY_MANIFEST => {
+            let manifest = SealedRecoveryManifest::from_ur(ur)?;
+            Ok(manifest.envelope().format())
+        }
+        TAG_NAME_DEPO_GET_RECOVERY_RESULT => result::<GetRecoveryResult>(ur),
+        TAG_NAME_DEPO_GET_SHARES_RESULT => result::<GetSharesResult>(ur),
+        TAG_NAME_DEPO_GET_XID_DOCUMENT_RESULT => {
+            result::<GetXIDDocumentResult>(ur)
+        }
+        TAG_NAME_DEPO_LIST_SHARES_RESULT => result::<ListSharesResult>(ur),
+        TAG_NAME_DEPO_OK_RESULT => result::<OkResult>(ur),
+        TAG_NAME_DEPO_START_RECOVERY_RESULT => {
+            result::<StartRecoveryResult>(ur)
+        }
+        TAG_NAME_DEPO_STORE_SHARE_RESULT => result::<StoreShareResult>(ur),
+        TAG_NAME_DEPO_STORE_SHARES_RESULT => result::<StoreSharesResult>(ur),
+        // Every request type, including `depo-request`, is an expression
+        // naming its function.
+        ur_type if ur_type.starts_with("depo-") => {
+            let envelope = Envelope::from_untagged_cbor(ur.cbor())?;
+            let expression = Expression::try_from(envelope.clone())?;
+            let request = DepoRequest::try_from(expression)?;
+            Ok(describe(request, &envelope))
+        }
+        ur_type => Err(format!("unsupported UR type: {}", ur_type).into()),
+    }
+}
@@ -0,0 +1,460 @@
+use bc_components::{
+    ARID, PrivateKeyBase, PrivateKeys, PrivateKeysProvider, PublicKeysProvider,
+    XID, XIDProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::{
+    DeleteAccount, DeleteShares, DepoFunction, Error, FinishRecovery,
+    GetRecovery, GetShares, GetSharesResult, GetXIDDocument, ListShares,
+    ListSharesResult, Receipt, RecoveryMethod, Result, ShareMetadata,
+    StartRecovery, StoreShare, StoreShares, UpdateRecovery, UpdateXIDDocument,
+};
+
+/// How long, in seconds, a sealed request remains valid after it is sent.
+pub const REQUEST_LIFETIME: f64 = 60.0;
+
+/// Carries sealed request envelopes to a depo and returns its sealed
+/// response envelopes.
+pub trait Transport {
+    fn send(&self, request: Envelope) -> Result<Envelope>;
+}
+
+/// A transport that hands requests to a function in the same process.
+pub struct InProcessTransport(Box<dyn Fn(Envelope) -> Result<Envelope>>);
+
+impl InProcessTransport {
+    pub fn new(f: impl Fn(Envelope) -> Result<Envelope> + 'static) -> Self {
+        Self(Box::new(f))
+    }
+}
+
+impl Transport for InProcessTransport {
+    fn send(&self, request: Envelope) -> Result<Envelope> { (self.0)(request) }
+}
+
+/// A client that seals requests from a user to a depo, sends them over a
+/// `Transport`, and decodes the typed results.
+pub struct DepoClient {
+    private_keys: PrivateKeys,
+    xid_document: XIDDocument,
+    depo_xid_document: XIDDocument,
+    transport: Box<dyn Transport>,
+    verify_shares_for: Option<XID>,
+}
+
+impl DepoClient {
+    pub fn new(
+        private_keys: PrivateKeys,
+        xid_document: XIDDocument,
+        depo_xid_document: XIDDocument,
+        transport: impl Transport + 'static,
+    ) -> Self {
+        Self {
+            private_keys,
+            xid_document,
+            depo_xid_document,
+            transport: Box::new(transport),
+            verify_shares_for: None,
+        }
+    }
+
+    pub fn new_with_private_key_base(
+        private_key_base: &PrivateKeyBase,
+        depo_xid_document: XIDDocument,
+        transport: impl Transport + 'static,
+    ) -> Self {
+        Self::new(
+            private_key_base.private_keys(),
+            private_key_base.public_keys().into(),
+            depo_xid_document,
+            transport,
+        )
+    }
+
+    /// Makes `get_shares` and `get_all_shares` fail with
+    /// `Error::ShareMismatch` if any returned share does not match its
+    /// receipt, or `Error::UnexpectedShares` if the depo returned shares
+    /// that were not requested. Requested shares the depo did not return are
+    /// left out of the result, as after they are deleted.
+    ///
+    /// `user_id` is the XID the account was created with, which receipts
+    /// are bound to, even after the account is recovered by other keys.
+    pub fn with_share_verification(mut self, user_id: XID) -> Self {
+        self.verify_shares_for = Some(user_id);
+        self
+    }
+
+    pub fn xid_document(&self) -> &XIDDocument { &self.xid_document }
+
+    pub fn depo_xid_document(&self) -> &XIDDocument { &self.depo_xid_document }
+
+    /// Seals `request` to the depo, sends it, and decodes the response as
+    /// the request's result type.
+    ///
+    /// The response must be signed by the verification key of the depo's
+    /// XID document, or the call fails with `Error::UnexpectedSender`.
+    pub fn send<F: DepoFunction>(&self, request: F) -> Result<F::Response> {
+        let id = ARID::new();
+        let now = Date::now();
+        let valid_until =
+            Date::from_timestamp(now.timestamp() + REQUEST_LIFETIME);
+        let sealed_request = SealedRequest::new_with_body(
+            request.into(),
+            id,
+            self.xid_document.clone(),
+        )
+        .with_date(now);
+        let request_envelope = sealed_request.to_envelope(
+            Some(valid_until),
+            Some(&self.private_keys),
+            Some(&self.depo_xid_document),
+        )?;
+        let response_envelope = self.transport.send(request_envelope)?;
+        let response = SealedResponse::try_from_encrypted_envelope(
+            &response_envelope,
+            Some(id),
+            None,
+            &self.private_keys,
+        )?;
+        let sender = response.sender();
+        if sender.xid() != self.depo_xid_document.xid()
+            || sender.verification_key()
+                != self.depo_xid_document.verification_key()
+        {
+            return Err(Error::UnexpectedSender {
+                expected: self.depo_xid_document.xid().to_string(),
+                found: sender.xid().to_string(),
+            });
+        }
+        F::Response::try_from(response)
+    }
+
+    pub fn store_share(&self, data: impl Into<ByteString>) -> Result<Receipt> {
+        Ok(self.send(StoreShare::new(data))?.receipt().clone())
+    }
+
+    /// Stores a share along with a label, content type or application tag
+    /// the depo will return with it.
+    pub fn store_share_with_metadata(
+        &self,
+        data: impl Into<ByteString>,
+        metadata: ShareMetadata,
+    ) -> Result<Receipt> {
+        let request = StoreShare::new_with_metadata(data, metadata);
+        Ok(self.send(request)?.receipt().clone())
+    }
+
+    /// Stores a batch of shares in one request, returning their receipts in
+    /// the same order. Either every share is stored or none is.
+    ///
+    /// Fails with `Error::ReceiptCountMismatch` if the depo does not return
+    /// one receipt for each share.
+    pub fn store_shares<I, T>(&self, data: I) -> Result<Vec<Receipt>>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<ByteString>,
+    {
+        let request = StoreShares::new_from_data(data);
+        let expected = request.shares().len();
+        let receipts = self.send(request)?.receipts().to_vec();
+        if receipts.len() != expected {
+            return Err(Error::ReceiptCountMismatch {
+                expected,
+                found: receipts.len(),
+            });
+        }
+        Ok(receipts)
+    }
+
+    pub fn get_shares<I, T>(&self, receipts: I) -> Result<GetSharesResult>
+    where
+        I: IntoIterator<Item = T>,
+        T: Clone + Into<Receipt>,
+    {
+        self.send_get_shares(GetShares::new(receipts))
+    }
+
+    pub fn get_all_shares(&self) -> Result<GetSharesResult> {
+        self.send_get_shares(GetShares::new_all_shares())
+    }
+
+    fn send_get_shares(&self, request: GetShares) -> Result<GetSharesResult> {
+        let result = self.send(request.clone())?;
+        if let Some(user_id) = &self.verify_shares_for {
+            let verification = result.verify(user_id, &request);
+            if !verification.mismatched().is_empty() {
+                return Err(Error::ShareMismatch {
+                    count: verification.mismatched().len(),
+                });
+            }
+            if !verification.unexpected().is_empty() {
+                return Err(Error::UnexpectedShares {
+                    count: verification.unexpected().len(),
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Lists the receipts and metadata of the account's shares, a page at a
+    /// time. Pass `None` as `cursor` for the first page, then the previous
+    /// page's `next_cursor` until it is `None`.
+    pub fn list_shares(
+        &self,
+        limit: Option<usize>,
+        cursor: Option<ByteString>,
+    ) -> Result<ListSharesResult> {
+        let mut request = ListShares::new();
+        if let Some(limit) = limit {
+            request = request.with_limit(limit);
+        }
+        if let Some(cursor) = cursor {
+            request = request.with_cursor(cursor);
+        }
+        self.send(request)
+    }
+
+    pub fn delete_shares<I, T>(&self, receipts: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: Clone + Into<Receipt>,
+    {
+        self.send(DeleteShares::new(receipts))?;
+        Ok(())
+    }
+
+    pub fn update_recovery(
+        &self,
+        recovery: Option<RecoveryMethod>,
+    ) -> Result<()> {
+        self.send(UpdateRecovery::new(recovery))?;
+        Ok(())
+    }
+
+    pub fn get_recovery(&self) -> Result<Option<RecoveryMethod>> {
+        Ok(self.send(GetRecovery::new())?.recovery().cloned())
+    }
+
+    /// The XID document the depo holds for the account, to confirm its
+    /// state after a key rotation or recovery.
+    pub fn get_xid_document(&self) -> Result<XIDDocument> {
+        Ok(self.send(GetXIDDocument::new())?.xid_document().clone())
+    }
+
+    pub fn update_xid_document(
+        &self,
+        new_xid_document: XIDDocument,
+    ) -> Result<()> {
+        self.send(UpdateXIDDocument::new(new_xid_document))?;
+        Ok(())
+    }
+
+    /// Replaces the account's XID document only if the depo still holds the
+    /// one it returns now, with proof, signed by this client's keys, that the
+    /// current controller approves.
+    ///
+    /// If the new document drops this client's key, the client can no longer
+    /// act on the account; use a client holding one of the new keys instead.
+    pub fn rotate_xid_document(
+        &self,
+        new_xid_document: XIDDocument,
+    ) -> Result<()> {
+        let current = self.get_xid_document()?;
+        let request = UpdateXIDDocument::new(new_xid_document)
+            .with_previous_xid_document(&current)
+            .with_proof(&current.xid(), &self.private_keys)?;
+        self.send(request)?;
+        Ok(())
+    }
+
+    /// Starts recovering an account using `recovery`, returning the sealed
+    /// continuation if the depo chose to return it directly rather than
+    /// deliver it out of band.
+    pub fn start_recovery(
+        &self,
+        recovery: RecoveryMethod,
+    ) -> Result<Option<Envelope>> {
+        Ok(self
+            .send(StartRecovery::new(recovery))?
+            .continuation()
+            .cloned())
+    }
+
+    pub fn finish_recovery(&self, continuation: Envelope) -> Result<()> {
+        self.send(FinishRecovery::new(continuation))?;
+        Ok(())
+    }
+
+    pub fn delete_account(&self) -> Result<()> {
+        self.send(DeleteAccount::new())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bc_rand::make_fake_random_number_generator;
+
+    use super::*;
+    use crate::{
+        DepoRequest, GetRecoveryResult, StoreShareResult, StoreSharesResult,
+    };
+
+    /// A stand-in depo that answers `storeShare` and `getRecovery`, answers
+    /// `storeShares` with a receipt for each share but the last, and
+    /// answers `getShares` with data that doesn't match the receipts, other
+    /// than for a share of `"genuine"`, which it returns along with a share
+    /// that was not requested.
+    ///
+    /// Responses are signed by `signer`, which may differ from the depo to
+    /// stand in for a forger that intercepts the request.
+    fn stub_depo(
+        private_key_base: PrivateKeyBase,
+        signer: PrivateKeyBase,
+    ) -> (XIDDocument, InProcessTransport) {
+        let depo_xid_document: XIDDocument =
+            private_key_base.public_keys().into();
+        let sender: XIDDocument = signer.public_keys().into();
+        let transport = InProcessTransport::new(move |envelope| {
+            let private_keys = private_key_base.private_keys();
+            let signer_keys = signer.private_keys();
+            let request = SealedRequest::try_from_envelope(
+                &envelope,
+                None,
+                None,
+                &private_keys,
+            )?;
+            let user_id = request.sender().xid();
+            let response = match DepoRequest::try_from(request.body().clone())?
+            {
+                DepoRequest::StoreShare(store_share) => {
+                    SealedResponse::new_success(request.id(), sender.clone())
+                        .with_result(StoreShareResult::new(Receipt::new(
+                            user_id,
+                            store_share.data(),
+                        )))
+                }
+                DepoRequest::StoreShares(store_shares) => {
+                    let shares = store_shares.shares();
+                    let receipts = shares[..shares.len().saturating_sub(1)]
+                        .iter()
+                        .map(|share| Receipt::new(user_id, share.data()))
+                        .collect();
+                    SealedResponse::new_success(request.id(), sender.clone())
+                        .with_result(StoreSharesResult::new(receipts))
+                }
+                DepoRequest::GetShares(get_shares) => {
+                    let genuine = Receipt::new(user_id, b"genuine");
+                    let mut receipt_to_data: HashMap<_, ByteString> =
+                        get_shares
+                            .receipts()
+                            .iter()
+                            .map(|receipt| (receipt.clone(), b"forged".into()))
+                            .collect();
+                    if receipt_to_data.contains_key(&genuine) {
+                        receipt_to_data.insert(genuine, b"genuine".into());
+                        receipt_to_data.insert(
+                            Receipt::new(user_id, b"extra"),
+                            b"extra".into(),
+                        );
+                    }
+                    SealedResponse::new_success(request.id(), sender.clone())
+                        .with_result(GetSharesResult::new(receipt_to_data))
+                }
+                DepoRequest::GetRecovery(_) => {
+                    SealedResponse::new_success(request.id(), sender.clone())
+                        .with_result(GetRecoveryResult::new(None))
+                }
+                _ => {
+                    return Err(Error::Transport {
+                        message: "unsupported".to_string(),
+                    });
+                }
+            };
+            Ok(response.to_envelope(
+                None,
+                Some(&signer_keys),
+                Some(request.sender()),
+            )?)
+        });
+        (depo_xid_document, transport)
+    }
+
+    #[test]
+    fn test_client() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let (depo_xid_document, transport) =
+            stub_depo(depo_keys.clone(), depo_keys);
+
+        let client = DepoClient::new_with_private_key_base(
+            &user_keys,
+            depo_xid_document,
+            transport,
+        );
+        let user_id = client.xid_document().xid();
+
+        let receipt = client.store_share(b"data").unwrap();
+        assert_eq!(receipt, Receipt::new(user_id, b"data"));
+
+        assert_eq!(client.get_recovery().unwrap(), None);
+
+        // A batch is refused unless every share gets a receipt.
+        assert!(matches!(
+            client.store_shares([b"one", b"two"]),
+            Err(Error::ReceiptCountMismatch { expected: 2, found: 1 })
+        ));
+
+        // Forged shares are returned as is unless verification is enabled.
+        let result = client.get_shares(vec![receipt.clone()]).unwrap();
+        let data = result.data_for_receipt(&receipt).unwrap();
+        assert_eq!(data.data(), b"forged");
+        let client = client.with_share_verification(user_id);
+        assert!(matches!(
+            client.get_shares(vec![receipt]),
+            Err(Error::ShareMismatch { count: 1 })
+        ));
+
+        // Shares that were not requested are refused too.
+        let receipt = client.store_share(b"genuine").unwrap();
+        assert!(matches!(
+            client.get_shares(vec![receipt]),
+            Err(Error::UnexpectedShares { count: 1 })
+        ));
+
+        assert!(matches!(
+            client.delete_account(),
+            Err(Error::Transport { .. })
+        ));
+    }
+
+    #[test]
+    fn test_forged_response() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let forger_keys = PrivateKeyBase::new_using(&mut rng);
+        let (depo_xid_document, transport) =
+            stub_depo(depo_keys, forger_keys);
+
+        let client = DepoClient::new_with_private_key_base(
+            &user_keys,
+            depo_xid_document,
+            transport,
+        );
+        assert!(matches!(
+            client.get_recovery(),
+            Err(Error::UnexpectedSender { .. })
+        ));
+    }
+}
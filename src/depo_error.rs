@@ -0,0 +1,327 @@
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+pub const DEPO_ERROR_TYPE: &str = "DepoError";
+
+pub const MESSAGE_PREDICATE: &str = "message";
+
+/// The reasons a depo gives for refusing a request.
+///
+/// Servers seal these into failure responses, and clients decode them into
+/// `Error::Remote` so callers can tell one refusal from another. Each is
+/// encoded as its code, with a human-readable message for older peers:
+///
+/// ```text
+/// "unknownAccount" [
+///     'isA': "DepoError"
+///     "message": "unknown account"
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DepoError {
+    /// The sender does not control an account on the depo.
+    #[error("unknown account")]
+    UnknownAccount,
+
+    /// The sender's keys no longer control the account, which has been
+    /// recovered by other keys.
+    #[error("account has been recovered")]
+    AccountRecovered,
+
+    /// A requested share is not held by the depo.
+    #[error("share not found")]
+    ShareNotFound,
+
+    /// Storing the share would exceed the account's quota.
+    #[error("quota exceeded")]
+    QuotaExceeded,
+
+    /// The request's signature could not be verified.
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    /// The request was malformed or its parameters were unacceptable.
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
+    /// The recovery method is already in use by another account.
+    #[error("recovery method already in use")]
+    RecoveryMethodInUse,
+
+    /// No account uses the recovery method.
+    #[error("unknown recovery method")]
+    UnknownRecoveryMethod,
+
+    /// The recovery continuation was not issued by the depo for the sender.
+    #[error("invalid recovery continuation")]
+    InvalidContinuation,
+
+    /// The recovery continuation has expired.
+    #[error("recovery continuation has expired")]
+    RecoveryExpired,
+
+    /// The recovery continuation has already been used.
+    #[error("recovery continuation already used")]
+    ContinuationUsed,
+
+    /// The key that signed the request is not allowed to call the function.
+    #[error("permission denied")]
+    PermissionDenied,
+
+    /// The account's XID document is not the one the update expected.
+    #[error("XID document has changed")]
+    XIDDocumentChanged,
+
+    /// The request is dated too far in the past or future, or is undated.
+    #[error("stale request")]
+    StaleRequest,
+
+    /// A request with the same ID has already been carried out.
+    #[error("replayed request")]
+    ReplayedRequest,
+
+    /// The depo does not support the function.
+    #[error("unsupported function")]
+    Unsupported,
+
+    /// The depo failed while carrying out the request. The details stay on
+    /// the depo; see `DepoHandler::report_internal_error`.
+    #[error("internal error")]
+    Internal,
+
+    /// An error this version does not recognize, by its message.
+    #[error("{0}")]
+    Other(String),
+}
+
+impl DepoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownAccount => "unknownAccount",
+            Self::AccountRecovered => "accountRecovered",
+            Self::ShareNotFound => "shareNotFound",
+            Self::QuotaExceeded => "quotaExceeded",
+            Self::InvalidSignature => "invalidSignature",
+            Self::InvalidRequest(_) => "invalidRequest",
+            Self::RecoveryMethodInUse => "recoveryMethodInUse",
+            Self::UnknownRecoveryMethod => "unknownRecoveryMethod",
+            Self::InvalidContinuation => "invalidContinuation",
+            Self::RecoveryExpired => "recoveryExpired",
+            Self::ContinuationUsed => "continuationUsed",
+            Self::PermissionDenied => "permissionDenied",
+            Self::XIDDocumentChanged => "xidDocumentChanged",
+            Self::StaleRequest => "staleRequest",
+            Self::ReplayedRequest => "replayedRequest",
+            Self::Unsupported => "unsupported",
+            Self::Internal => "internal",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// Decodes the error envelope of a failure response.
+    ///
+    /// Unlike `try_from`, this never fails: errors from older depos, which
+    /// send a plain string, and errors with unknown codes become `Other`.
+    pub fn from_response_error(envelope: &Envelope) -> Self {
+        Self::try_from(envelope.clone()).unwrap_or_else(|_| {
+            let message = envelope
+                .extract_object_for_predicate::<String>(MESSAGE_PREDICATE)
+                .or_else(|_| envelope.extract_subject::<String>())
+                .unwrap_or_else(|_| envelope.format_flat());
+            Self::Other(message)
+        })
+    }
+
+    fn from_code(code: &str, message: String) -> Option<Self> {
+        Some(match code {
+            "unknownAccount" => Self::UnknownAccount,
+            "accountRecovered" => Self::AccountRecovered,
+            "shareNotFound" => Self::ShareNotFound,
+            "quotaExceeded" => Self::QuotaExceeded,
+            "invalidSignature" => Self::InvalidSignature,
+            "invalidRequest" => Self::InvalidRequest(message),
+            "recoveryMethodInUse" => Self::RecoveryMethodInUse,
+            "unknownRecoveryMethod" => Self::UnknownRecoveryMethod,
+            "invalidContinuation" => Self::InvalidContinuation,
+            "recoveryExpired" => Self::RecoveryExpired,
+            "continuationUsed" => Self::ContinuationUsed,
+            "permissionDenied" => Self::PermissionDenied,
+            "xidDocumentChanged" => Self::XIDDocumentChanged,
+            "staleRequest" => Self::StaleRequest,
+            "replayedRequest" => Self::ReplayedRequest,
+            "unsupported" => Self::Unsupported,
+            "internal" => Self::Internal,
+            "other" => Self::Other(message),
+            _ => return None,
+        })
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            Self::InvalidRequest(message) | Self::Other(message) => {
+                message.clone()
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Classifies an error raised while handling a request, so the sender
+/// learns why it was refused.
+///
+/// Errors that are not the sender's doing become `Internal`, which carries
+/// no details, so nothing about the depo's workings leaks to the sender.
+impl From<&Error> for DepoError {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Remote(error) => error.clone(),
+            Error::InvalidEnvelope { .. }
+            | Error::MissingParameter { .. }
+            | Error::InvalidParameter { .. }
+            | Error::TypeMismatch { .. }
+            | Error::UnexpectedFunction { .. }
+            | Error::UnexpectedParameter { .. }
+            | Error::DuplicateParameter { .. }
+            | Error::InvalidReceipt { .. }
+            | Error::InvalidDigest { .. }
+            | Error::InvalidRecoveryMethod { .. }
+            | Error::EnvelopeProcessing(_)
+            | Error::DcborProcessing(_) => {
+                Self::InvalidRequest(error.to_string())
+            }
+            Error::UnknownFunction { .. } => Self::Unsupported,
+            _ => Self::Internal,
+        }
+    }
+}
+
+impl From<Error> for DepoError {
+    fn from(error: Error) -> Self { Self::from(&error) }
+}
+
+impl From<DepoError> for Envelope {
+    fn from(value: DepoError) -> Self {
+        Envelope::new(value.code())
+            .add_type(DEPO_ERROR_TYPE)
+            .add_assertion(MESSAGE_PREDICATE, value.detail())
+    }
+}
+
+impl TryFrom<Envelope> for DepoError {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        envelope
+            .check_type(DEPO_ERROR_TYPE)
+            .map_err(|e| Error::TypeMismatch {
+                expected: DEPO_ERROR_TYPE.to_string(),
+                found: format!("envelope without type or wrong type: {}", e),
+            })?;
+        let invalid = |e: bc_envelope::Error| Error::InvalidEnvelope {
+            message: format!("invalid depo error: {}", e),
+        };
+        let code: String = envelope.extract_subject().map_err(invalid)?;
+        let message: String = envelope
+            .extract_object_for_predicate(MESSAGE_PREDICATE)
+            .map_err(invalid)?;
+        Self::from_code(&code, message).ok_or_else(|| Error::InvalidEnvelope {
+            message: format!("unknown depo error code: {}", code),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_depo_error() {
+        bc_envelope::register_tags();
+
+        let error = DepoError::UnknownAccount;
+        let envelope = error.to_envelope();
+        // println!("{}", envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(envelope.format(), indoc! {r#"
+            "unknownAccount" [
+                'isA': "DepoError"
+                "message": "unknown account"
+            ]
+        "#}.trim());
+
+        for error in [
+            DepoError::UnknownAccount,
+            DepoError::AccountRecovered,
+            DepoError::ShareNotFound,
+            DepoError::QuotaExceeded,
+            DepoError::InvalidSignature,
+            DepoError::InvalidRequest("missing data".to_string()),
+            DepoError::RecoveryMethodInUse,
+            DepoError::UnknownRecoveryMethod,
+            DepoError::InvalidContinuation,
+            DepoError::RecoveryExpired,
+            DepoError::ContinuationUsed,
+            DepoError::PermissionDenied,
+            DepoError::XIDDocumentChanged,
+            DepoError::StaleRequest,
+            DepoError::ReplayedRequest,
+            DepoError::Unsupported,
+            DepoError::Internal,
+            DepoError::Other("out of cheese".to_string()),
+        ] {
+            let decoded = DepoError::try_from(error.to_envelope()).unwrap();
+            assert_eq!(error, decoded);
+        }
+    }
+
+    #[test]
+    fn test_response_error() {
+        bc_envelope::register_tags();
+
+        // Older depos send the reason as a plain string.
+        let envelope = Envelope::new("request rejected");
+        assert_eq!(
+            DepoError::from_response_error(&envelope),
+            DepoError::Other("request rejected".to_string())
+        );
+
+        // Codes from newer depos keep their message.
+        let envelope = Envelope::new("tooManyRequests")
+            .add_type(DEPO_ERROR_TYPE)
+            .add_assertion(MESSAGE_PREDICATE, "slow down");
+        assert!(DepoError::try_from(envelope.clone()).is_err());
+        assert_eq!(
+            DepoError::from_response_error(&envelope),
+            DepoError::Other("slow down".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_error() {
+        let error = Error::MissingParameter { parameter: "data".to_string() };
+        assert_eq!(
+            DepoError::from(error),
+            DepoError::InvalidRequest(
+                "missing required parameter: data".to_string()
+            )
+        );
+        assert_eq!(
+            DepoError::from(Error::Remote(DepoError::QuotaExceeded)),
+            DepoError::QuotaExceeded
+        );
+        let error = Error::UnknownFunction { function: "foo".to_string() };
+        assert_eq!(DepoError::from(error), DepoError::Unsupported);
+
+        // Internal failures are not described to the sender.
+        let error = Error::Transport { message: "disk full".to_string() };
+        let error = DepoError::from(error);
+        assert_eq!(error, DepoError::Internal);
+        let envelope = error.to_envelope();
+        let message: String =
+            envelope.extract_object_for_predicate(MESSAGE_PREDICATE).unwrap();
+        assert_eq!(message, "internal error");
+    }
+}
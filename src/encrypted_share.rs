@@ -0,0 +1,184 @@
+use bc_components::{PrivateKeyBase, SymmetricKey};
+use bc_envelope::prelude::*;
+
+use crate::{GetSharesResult, Receipt, Result, ShareMetadata, StoreShare};
+
+/// Distinguishes the share key derived from a user's keys from any other
+/// key derived from them. `bc_crypto`'s HKDF takes no info parameter, so
+/// this is passed as its salt, which serves the same purpose.
+const SHARE_KEY_INFO: &[u8] = b"depo-share-key";
+
+/// Encrypts share payloads on the client, so the depo only ever holds
+/// ciphertext.
+///
+/// Each payload is wrapped in an envelope whose subject is encrypted with a
+/// symmetric key, either supplied explicitly or derived from the user's
+/// keys. Payloads can optionally be compressed before they are encrypted.
+/// The encrypted envelope's CBOR is what gets stored, so receipts are
+/// computed over the ciphertext.
+#[derive(Debug, Clone)]
+pub struct EncryptedShare {
+    key: SymmetricKey,
+    compress: bool,
+}
+
+impl EncryptedShare {
+    pub fn new(key: SymmetricKey) -> Self { Self { key, compress: false } }
+
+    /// Uses a key derived from the user's `private_key_base`, so shares can
+    /// be decrypted by anyone holding the same keys without storing a
+    /// separate key.
+    pub fn new_with_private_key_base(
+        private_key_base: &PrivateKeyBase,
+    ) -> Self {
+        let agreement_key = private_key_base.x25519_private_key();
+        let key = bc_crypto::hkdf_hmac_sha256(
+            agreement_key.data(),
+            SHARE_KEY_INFO,
+            SymmetricKey::SYMMETRIC_KEY_SIZE,
+        );
+        Self::new(SymmetricKey::from_data(
+            key.try_into().expect("HKDF returns the requested length"),
+        ))
+    }
+
+    /// Compresses payloads before encrypting them.
+    pub fn with_compression(mut self) -> Self {
+        self.compress = true;
+        self
+    }
+
+    pub fn key(&self) -> &SymmetricKey { &self.key }
+
+    pub fn is_compressing(&self) -> bool { self.compress }
+
+    /// Encrypts `data`, returning the bytes to store as the share.
+    pub fn encrypt(&self, data: impl AsRef<[u8]>) -> Result<ByteString> {
+        let mut envelope = Envelope::new(ByteString::from(data.as_ref()));
+        if self.compress {
+            envelope = envelope.compress()?;
+        }
+        Ok(envelope.encrypt(&self.key).to_cbor_data().into())
+    }
+
+    /// Decrypts a share produced by `encrypt`, whether or not it was
+    /// compressed.
+    pub fn decrypt(&self, share: impl AsRef<[u8]>) -> Result<ByteString> {
+        let envelope = Envelope::try_from(CBOR::try_from_data(share)?)?;
+        let mut envelope = envelope.decrypt(&self.key)?;
+        if envelope.is_compressed() {
+            envelope = envelope.decompress()?;
+        }
+        Ok(envelope.extract_subject()?)
+    }
+
+    /// A `StoreShare` request for the encrypted `data`.
+    pub fn store_share(&self, data: impl AsRef<[u8]>) -> Result<StoreShare> {
+        Ok(StoreShare::new(self.encrypt(data)?))
+    }
+
+    /// A `StoreShare` request for the encrypted `data`, with `metadata`,
+    /// which is not encrypted.
+    pub fn store_share_with_metadata(
+        &self,
+        data: impl AsRef<[u8]>,
+        metadata: ShareMetadata,
+    ) -> Result<StoreShare> {
+        Ok(StoreShare::new_with_metadata(self.encrypt(data)?, metadata))
+    }
+
+    /// Decrypts the share `result` holds for `receipt`, or returns `None`
+    /// if it holds none.
+    pub fn decrypt_from(
+        &self,
+        result: &GetSharesResult,
+        receipt: &Receipt,
+    ) -> Result<Option<ByteString>> {
+        result
+            .data_for_receipt(receipt)
+            .map(|share| self.decrypt(share))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::XID;
+    use bc_rand::make_fake_random_number_generator;
+
+    use super::*;
+    use crate::Error;
+
+    fn data() -> Vec<u8> { "seed backup ".repeat(100).into_bytes() }
+
+    #[test]
+    fn test_encrypted_share() {
+        bc_envelope::register_tags();
+
+        let encrypted_share = EncryptedShare::new(SymmetricKey::new());
+        let share = encrypted_share.encrypt(data()).unwrap();
+        assert_ne!(share.data(), data().as_slice());
+        assert_eq!(encrypted_share.decrypt(&share).unwrap().data(), data());
+
+        // Compression applies before encryption, so it still pays off.
+        let compressing = encrypted_share.clone().with_compression();
+        let compressed = compressing.encrypt(data()).unwrap();
+        assert!(compressed.len() < share.len());
+        let decrypted = encrypted_share.decrypt(&compressed).unwrap();
+        assert_eq!(decrypted.data(), data());
+
+        let other = EncryptedShare::new(SymmetricKey::new());
+        assert!(matches!(
+            other.decrypt(&share),
+            Err(Error::EnvelopeProcessing(_))
+        ));
+    }
+
+    #[test]
+    fn test_derived_key() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let encrypted_share =
+            EncryptedShare::new_with_private_key_base(&user_keys);
+        let share = encrypted_share.encrypt(data()).unwrap();
+
+        // The same keys derive the same share key.
+        let rederived = EncryptedShare::new_with_private_key_base(&user_keys);
+        assert_eq!(rederived.key(), encrypted_share.key());
+        assert_eq!(rederived.decrypt(&share).unwrap().data(), data());
+
+        let other_keys = PrivateKeyBase::new_using(&mut rng);
+        let other = EncryptedShare::new_with_private_key_base(&other_keys);
+        assert!(other.decrypt(&share).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_from() {
+        bc_envelope::register_tags();
+
+        let user_id = XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap();
+        let encrypted_share = EncryptedShare::new(SymmetricKey::new());
+        let request = encrypted_share.store_share(b"data").unwrap();
+        let receipt = Receipt::new(user_id, request.data());
+        let result = GetSharesResult::new(
+            vec![(receipt.clone(), ByteString::from(request.data()))]
+                .into_iter()
+                .collect(),
+        );
+        let data = encrypted_share.decrypt_from(&result, &receipt).unwrap();
+        assert_eq!(data.unwrap().data(), b"data");
+
+        let missing = Receipt::new(user_id, b"missing");
+        assert!(
+            encrypted_share
+                .decrypt_from(&result, &missing)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::DepoError;
+
 #[derive(Debug, Error)]
 pub enum Error {
     /// Invalid envelope structure
@@ -18,14 +20,74 @@ pub enum Error {
     #[error("envelope type mismatch: expected '{expected}', found '{found}'")]
     TypeMismatch { expected: String, found: String },
 
+    /// Expression names a function that is not part of the depo API
+    #[error("unknown function: {function}")]
+    UnknownFunction { function: String },
+
+    /// Expression invokes a different function than the one being decoded
+    #[error("unexpected function: expected '{expected}', found '{found}'")]
+    UnexpectedFunction { expected: String, found: String },
+
+    /// Parameter not accepted by the function being decoded
+    #[error("unexpected parameter: {parameter}")]
+    UnexpectedParameter { parameter: String },
+
+    /// Parameter that may appear only once was repeated
+    #[error("duplicate parameter: {parameter}")]
+    DuplicateParameter { parameter: String },
+
     /// Invalid receipt format
     #[error("invalid receipt format: {message}")]
     InvalidReceipt { message: String },
 
+    /// Recovery method that is malformed or of an unknown kind
+    #[error("invalid recovery method: {message}")]
+    InvalidRecoveryMethod { message: String },
+
+    /// Share data that decompresses to more than the depo accepts
+    #[error("share data decompresses to more than {max} bytes")]
+    ShareTooLarge { max: usize },
+
     /// Invalid digest data
     #[error("invalid digest data: {message}")]
     InvalidDigest { message: String },
 
+    /// The depo refused to carry out a request
+    #[error("depo error: {0}")]
+    Remote(#[from] DepoError),
+
+    /// Response signed by someone other than the depo it was sent to
+    #[error("unexpected sender: expected '{expected}', found '{found}'")]
+    UnexpectedSender { expected: String, found: String },
+
+    /// Shares returned by the depo do not match their receipts
+    #[error("{count} shares do not match their receipts")]
+    ShareMismatch { count: usize },
+
+    /// Shares returned by the depo that were not requested
+    #[error("{count} shares were returned but not requested")]
+    UnexpectedShares { count: usize },
+
+    /// Receipts returned by the depo that do not match the shares stored
+    #[error("expected {expected} receipts, found {found}")]
+    ReceiptCountMismatch { expected: usize, found: usize },
+
+    /// Shares that cannot be assigned to depos as requested
+    #[error("invalid share distribution: {message}")]
+    InvalidShareDistribution { message: String },
+
+    /// JSON that does not describe a valid value
+    #[error("invalid JSON: {message}")]
+    InvalidJson { message: String },
+
+    /// Multipart UR parts that do not yet make up a whole message
+    #[error("incomplete multipart UR")]
+    IncompleteUR,
+
+    /// The transport failed to deliver a request or return a response
+    #[error("transport failed: {message}")]
+    Transport { message: String },
+
     /// Envelope processing error
     #[error("envelope processing failed")]
     EnvelopeProcessing(#[from] bc_envelope::Error),
@@ -34,6 +96,18 @@ pub enum Error {
     #[error("DCBOR processing failed")]
     DcborProcessing(#[from] dcbor::Error),
 
+    /// UR processing error
+    #[error("UR processing failed")]
+    URProcessing(#[from] bc_ur::Error),
+
+    /// SSKR processing error
+    #[error("SSKR processing failed")]
+    SSKRProcessing(#[from] bc_components::SSKRError),
+
+    /// Cryptographic component error, such as a failure to sign
+    #[error("component processing failed")]
+    ComponentsProcessing(#[from] bc_components::Error),
+
     /// GSTP processing error
     #[error("GSTP processing failed")]
     GstpProcessing(#[from] gstp::Error),
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use gstp::prelude::*;
+
+use crate::{DepoError, Error, Result};
+
+/// How long, in seconds, a depo accepts a request after the date it was
+/// sealed, by default.
+pub const DEFAULT_MAX_REQUEST_AGE: f64 = 300.0;
+
+/// How far, in seconds, a request's date may be ahead of the depo's clock,
+/// by default.
+pub const DEFAULT_MAX_CLOCK_SKEW: f64 = 60.0;
+
+/// The window of dates within which a depo accepts a sealed request.
+///
+/// The expiry a sender sets is sealed in a continuation only the sender can
+/// open, so the depo cannot check it. The window instead bounds every
+/// request by the date it was sealed with, so a cache of request IDs only
+/// has to remember each ID until the window closes on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FreshnessPolicy {
+    max_age: f64,
+    max_skew: f64,
+}
+
+impl FreshnessPolicy {
+    pub fn new(max_age: f64, max_skew: f64) -> Self {
+        Self { max_age, max_skew }
+    }
+
+    pub fn max_age(&self) -> f64 { self.max_age }
+
+    pub fn max_skew(&self) -> f64 { self.max_skew }
+
+    /// Checks that `request` is dated within the window around `now`,
+    /// returning the date after which it will no longer be accepted.
+    ///
+    /// Undated requests are refused with `DepoError::StaleRequest`, like
+    /// those dated outside the window.
+    pub fn check(&self, request: &SealedRequest, now: &Date) -> Result<Date> {
+        let Some(date) = request.date() else {
+            return Err(Error::Remote(DepoError::StaleRequest));
+        };
+        let date = date.timestamp();
+        let now = now.timestamp();
+        if date > now + self.max_skew || date + self.max_age < now {
+            return Err(Error::Remote(DepoError::StaleRequest));
+        }
+        Ok(Date::from_timestamp(date + self.max_age))
+    }
+}
+
+impl Default for FreshnessPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_REQUEST_AGE, DEFAULT_MAX_CLOCK_SKEW)
+    }
+}
+
+/// Remembers the IDs of requests a depo has carried out, so a captured
+/// request cannot be carried out again.
+pub trait ReplayCache {
+    /// Whether `id` is recorded and has not yet expired at `now`.
+    fn contains(&mut self, id: &ARID, now: &Date) -> bool;
+
+    /// Records `id` as carried out until `expires`.
+    fn record(&mut self, id: ARID, expires: Date);
+}
+
+/// A `ReplayCache` held in memory, which forgets IDs once they expire.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReplayCache {
+    seen: HashMap<ARID, Date>,
+}
+
+impl MemoryReplayCache {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn len(&self) -> usize { self.seen.len() }
+
+    pub fn is_empty(&self) -> bool { self.seen.is_empty() }
+}
+
+impl ReplayCache for MemoryReplayCache {
+    fn contains(&mut self, id: &ARID, now: &Date) -> bool {
+        self.seen
+            .retain(|_, expires| expires.timestamp() > now.timestamp());
+        self.seen.contains_key(id)
+    }
+
+    fn record(&mut self, id: ARID, expires: Date) {
+        self.seen.insert(id, expires);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{PrivateKeyBase, PublicKeysProvider};
+    use bc_rand::make_fake_random_number_generator;
+    use bc_xid::XIDDocument;
+
+    use super::*;
+    use crate::DeleteAccount;
+
+    fn request(date: Option<f64>) -> SealedRequest {
+        let mut rng = make_fake_random_number_generator();
+        let sender: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        let request = SealedRequest::new_with_body(
+            DeleteAccount::new().into(),
+            ARID::new(),
+            sender,
+        );
+        match date {
+            Some(date) => request.with_date(Date::from_timestamp(date)),
+            None => request,
+        }
+    }
+
+    fn is_stale(result: Result<Date>) -> bool {
+        matches!(result, Err(Error::Remote(DepoError::StaleRequest)))
+    }
+
+    #[test]
+    fn test_freshness() {
+        let policy = FreshnessPolicy::default();
+        let now = Date::from_timestamp(1_000_000.0);
+
+        let expires = policy.check(&request(Some(1_000_000.0)), &now);
+        assert_eq!(expires.unwrap(), Date::from_timestamp(1_000_300.0));
+        assert!(policy.check(&request(Some(999_800.0)), &now).is_ok());
+        assert!(policy.check(&request(Some(1_000_030.0)), &now).is_ok());
+
+        assert!(is_stale(policy.check(&request(Some(999_600.0)), &now)));
+        assert!(is_stale(policy.check(&request(Some(1_000_100.0)), &now)));
+        assert!(is_stale(policy.check(&request(None), &now)));
+    }
+
+    #[test]
+    fn test_memory_replay_cache() {
+        let mut cache = MemoryReplayCache::new();
+        let id = ARID::new();
+        let now = Date::from_timestamp(1_000_000.0);
+        let expires = Date::from_timestamp(1_000_300.0);
+
+        assert!(!cache.contains(&id, &now));
+        cache.record(id, expires);
+        assert!(cache.contains(&id, &now));
+        cache.record(ARID::new(), expires);
+        assert_eq!(cache.len(), 2);
+
+        // Expired IDs are forgotten.
+        let later = Date::from_timestamp(1_000_301.0);
+        assert!(!cache.contains(&id, &later));
+        assert!(cache.is_empty());
+        cache.record(id, Date::from_timestamp(1_000_600.0));
+        assert!(cache.contains(&id, &later));
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,876 @@
+//! JSON forms of receipts, requests and results, for debugging and for
+//! bridging a depo to HTTP/JSON clients.
+//!
+//! Each form carries exactly the information of the type's envelope, so
+//! converting to JSON and back yields an identical envelope. Field names are
+//! camelCase, optional fields are omitted when absent, and values are
+//! encoded as follows:
+//!
+//! | Value | JSON |
+//! |-------|------|
+//! | share data, cursors, salts, digests | lowercase hex string |
+//! | signatures | lowercase hex string of their tagged CBOR |
+//! | `Receipt` | `ur:depo-receipt/...` string |
+//! | dates | seconds since the Unix epoch |
+//! | `RecoveryMethod` | `{"kind": "email", "value": "alice@example.com"}` |
+//! | continuations, XID documents | `ur:envelope/...` string |
+//!
+//! A `DepoRequest` is the JSON form of its request with a `function` field
+//! naming the depo function:
+//!
+//! ```json
+//! {
+//!   "function": "storeShare",
+//!   "data": "64617461",
+//!   "label": "Group 1"
+//! }
+//! ```
+//!
+//! `getShares` and `listShares` results list their shares as objects with a
+//! `receipt` field alongside the share's metadata fields:
+//!
+//! ```json
+//! {
+//!   "shares": [
+//!     { "receipt": "ur:depo-receipt/...", "data": "64617461", "size": 4 }
+//!   ]
+//! }
+//! ```
+
+use bc_components::{Digest, Salt, Signature};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{DeserializeOwned, Error as _},
+};
+
+use crate::{
+    DeleteAccount, DeleteShares, DepoRequest, Error, FinishRecovery,
+    GetRecovery, GetRecoveryResult, GetShares, GetSharesResult, GetXIDDocument,
+    GetXIDDocumentResult, ListShares, ListSharesResult, OkResult, Receipt,
+    RecoveryMethod, Result, ShareMetadata, StartRecovery, StartRecoveryResult,
+    StoreShare, StoreShareResult, StoreShares, StoreSharesResult, StoredShare,
+    UpdateRecovery, UpdateXIDDocument,
+};
+
+/// A type with a JSON form distinct from its Rust representation.
+trait JsonForm: Sized {
+    type Json: Serialize + DeserializeOwned;
+
+    fn to_json(&self) -> Self::Json;
+
+    fn from_json(json: Self::Json) -> Result<Self>;
+}
+
+/// Implements `Serialize` and `Deserialize` through the type's `JsonForm`.
+macro_rules! impl_serde {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                self.to_json().serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                let json =
+                    <$type as JsonForm>::Json::deserialize(deserializer)?;
+                Self::from_json(json).map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+fn invalid_json(message: impl std::fmt::Display) -> Error {
+    Error::InvalidJson { message: message.to_string() }
+}
+
+fn from_hex(hex: &str) -> Result<ByteString> {
+    Ok(hex::decode(hex).map_err(invalid_json)?.into())
+}
+
+fn envelope_to_ur(envelope: &Envelope) -> String { envelope.ur_string() }
+
+fn envelope_from_ur(ur_string: &str) -> Result<Envelope> {
+    Envelope::from_ur_string(ur_string).map_err(invalid_json)
+}
+
+fn xid_document_to_ur(xid_document: &XIDDocument) -> String {
+    envelope_to_ur(&xid_document.clone().into_envelope())
+}
+
+fn xid_document_from_ur(ur_string: &str) -> Result<XIDDocument> {
+    XIDDocument::try_from(envelope_from_ur(ur_string)?).map_err(invalid_json)
+}
+
+fn is_false(value: &bool) -> bool { !value }
+
+//
+// Shared values
+//
+
+impl JsonForm for Receipt {
+    type Json = String;
+
+    fn to_json(&self) -> String { self.ur_string() }
+
+    fn from_json(json: String) -> Result<Self> {
+        Self::from_ur_string(json).map_err(invalid_json)
+    }
+}
+impl_serde!(Receipt);
+
+#[derive(Serialize, Deserialize)]
+struct RecoveryMethodJson {
+    kind: String,
+    value: String,
+}
+
+impl JsonForm for RecoveryMethod {
+    type Json = RecoveryMethodJson;
+
+    fn to_json(&self) -> Self::Json {
+        RecoveryMethodJson {
+            kind: self.kind().to_string(),
+            value: self.value().to_string(),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Self::from_kind(&json.kind, json.value)
+    }
+}
+impl_serde!(RecoveryMethod);
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShareMetadataJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    application_tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stored_at: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+}
+
+impl JsonForm for ShareMetadata {
+    type Json = ShareMetadataJson;
+
+    fn to_json(&self) -> Self::Json {
+        ShareMetadataJson {
+            label: self.label().map(str::to_string),
+            content_type: self.content_type().map(str::to_string),
+            application_tag: self.application_tag().map(str::to_string),
+            stored_at: self.stored_at().map(Date::timestamp),
+            size: self.size(),
+            salt: self.salt().map(|salt| hex::encode(salt.as_bytes())),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let mut metadata = ShareMetadata::new();
+        if let Some(label) = json.label {
+            metadata = metadata.with_label(label);
+        }
+        if let Some(content_type) = json.content_type {
+            metadata = metadata.with_content_type(content_type);
+        }
+        if let Some(application_tag) = json.application_tag {
+            metadata = metadata.with_application_tag(application_tag);
+        }
+        if let Some(stored_at) = json.stored_at {
+            metadata = metadata.with_stored_at(Date::from_timestamp(stored_at));
+        }
+        if let Some(size) = json.size {
+            metadata = metadata.with_size(size);
+        }
+        if let Some(salt) = json.salt {
+            metadata = metadata.with_salt(Salt::from_data(from_hex(&salt)?));
+        }
+        Ok(metadata)
+    }
+}
+impl_serde!(ShareMetadata);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredShareJson {
+    data: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    compressed: bool,
+    #[serde(flatten)]
+    metadata: ShareMetadata,
+}
+
+impl JsonForm for StoredShare {
+    type Json = StoredShareJson;
+
+    fn to_json(&self) -> Self::Json {
+        StoredShareJson {
+            data: hex::encode(self.data()),
+            compressed: self.is_compressed(),
+            metadata: self.metadata().clone(),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let data = from_hex(&json.data)?;
+        let share = StoredShare::new_with_metadata(data, json.metadata);
+        Ok(if json.compressed { share.with_compression() } else { share })
+    }
+}
+impl_serde!(StoredShare);
+
+//
+// Requests
+//
+
+#[derive(Serialize, Deserialize)]
+struct EmptyJson {}
+
+impl JsonForm for DeleteAccount {
+    type Json = EmptyJson;
+
+    fn to_json(&self) -> Self::Json { EmptyJson {} }
+
+    fn from_json(_json: Self::Json) -> Result<Self> { Ok(Self::new()) }
+}
+impl_serde!(DeleteAccount);
+
+impl JsonForm for GetRecovery {
+    type Json = EmptyJson;
+
+    fn to_json(&self) -> Self::Json { EmptyJson {} }
+
+    fn from_json(_json: Self::Json) -> Result<Self> { Ok(Self::new()) }
+}
+impl_serde!(GetRecovery);
+
+#[derive(Serialize, Deserialize)]
+struct ReceiptsJson {
+    receipts: Vec<Receipt>,
+}
+
+impl JsonForm for DeleteShares {
+    type Json = ReceiptsJson;
+
+    fn to_json(&self) -> Self::Json {
+        ReceiptsJson { receipts: self.receipts().iter().cloned().collect() }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.receipts))
+    }
+}
+impl_serde!(DeleteShares);
+
+impl JsonForm for GetShares {
+    type Json = ReceiptsJson;
+
+    fn to_json(&self) -> Self::Json {
+        ReceiptsJson { receipts: self.receipts().iter().cloned().collect() }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.receipts))
+    }
+}
+impl_serde!(GetShares);
+
+impl JsonForm for GetXIDDocument {
+    type Json = EmptyJson;
+
+    fn to_json(&self) -> Self::Json { EmptyJson {} }
+
+    fn from_json(_json: Self::Json) -> Result<Self> { Ok(Self::new()) }
+}
+impl_serde!(GetXIDDocument);
+
+#[derive(Serialize, Deserialize)]
+struct ContinuationJson {
+    continuation: String,
+}
+
+impl JsonForm for FinishRecovery {
+    type Json = ContinuationJson;
+
+    fn to_json(&self) -> Self::Json {
+        ContinuationJson { continuation: envelope_to_ur(self.continuation()) }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(envelope_from_ur(&json.continuation)?))
+    }
+}
+impl_serde!(FinishRecovery);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListSharesJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    limit: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+impl JsonForm for ListShares {
+    type Json = ListSharesJson;
+
+    fn to_json(&self) -> Self::Json {
+        ListSharesJson {
+            limit: self.limit(),
+            cursor: self.cursor().map(hex::encode),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let mut request = Self::new();
+        if let Some(limit) = json.limit {
+            request = request.with_limit(limit);
+        }
+        if let Some(cursor) = json.cursor {
+            request = request.with_cursor(from_hex(&cursor)?);
+        }
+        Ok(request)
+    }
+}
+impl_serde!(ListShares);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryMethodFieldJson {
+    recovery_method: RecoveryMethod,
+}
+
+impl JsonForm for StartRecovery {
+    type Json = RecoveryMethodFieldJson;
+
+    fn to_json(&self) -> Self::Json {
+        RecoveryMethodFieldJson { recovery_method: self.recovery().clone() }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.recovery_method))
+    }
+}
+impl_serde!(StartRecovery);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OptionalRecoveryMethodJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recovery_method: Option<RecoveryMethod>,
+}
+
+impl JsonForm for UpdateRecovery {
+    type Json = OptionalRecoveryMethodJson;
+
+    fn to_json(&self) -> Self::Json {
+        OptionalRecoveryMethodJson {
+            recovery_method: self.recovery().cloned(),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.recovery_method))
+    }
+}
+impl_serde!(UpdateRecovery);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreShareJson {
+    data: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    compressed: bool,
+    #[serde(flatten)]
+    metadata: ShareMetadata,
+}
+
+impl JsonForm for StoreShare {
+    type Json = StoreShareJson;
+
+    fn to_json(&self) -> Self::Json {
+        StoreShareJson {
+            data: hex::encode(self.data()),
+            compressed: self.is_compressed(),
+            metadata: self.metadata().clone(),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let request =
+            Self::new_with_metadata(from_hex(&json.data)?, json.metadata);
+        Ok(if json.compressed { request.with_compression() } else { request })
+    }
+}
+impl_serde!(StoreShare);
+
+#[derive(Serialize, Deserialize)]
+struct StoreSharesJson {
+    shares: Vec<StoreShare>,
+}
+
+impl JsonForm for StoreShares {
+    type Json = StoreSharesJson;
+
+    fn to_json(&self) -> Self::Json {
+        StoreSharesJson { shares: self.shares().to_vec() }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.shares))
+    }
+}
+impl_serde!(StoreShares);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateXIDDocumentJson {
+    new_xid_document: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proof: Option<String>,
+}
+
+impl JsonForm for UpdateXIDDocument {
+    type Json = UpdateXIDDocumentJson;
+
+    fn to_json(&self) -> Self::Json {
+        UpdateXIDDocumentJson {
+            new_xid_document: xid_document_to_ur(self.new_xid_document()),
+            previous_digest: self.previous_digest().map(hex::encode),
+            proof: self.proof().map(|proof| hex::encode(proof.to_cbor_data())),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let mut request =
+            Self::new(xid_document_from_ur(&json.new_xid_document)?);
+        if let Some(previous_digest) = json.previous_digest {
+            let digest = Digest::from_data_ref(from_hex(&previous_digest)?)
+                .map_err(invalid_json)?;
+            request = request.with_previous_digest(digest);
+        }
+        if let Some(proof) = json.proof {
+            let cbor = CBOR::try_from_data(from_hex(&proof)?)?;
+            let proof = Signature::try_from(cbor).map_err(invalid_json)?;
+            request = request.with_proof_signature(proof);
+        }
+        Ok(request)
+    }
+}
+impl_serde!(UpdateXIDDocument);
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "function", rename_all = "camelCase")]
+#[allow(clippy::large_enum_variant)]
+enum DepoRequestJson {
+    DeleteAccount(DeleteAccount),
+    DeleteShares(DeleteShares),
+    FinishRecovery(FinishRecovery),
+    GetRecovery(GetRecovery),
+    GetShares(GetShares),
+    #[serde(rename = "getXIDDocument")]
+    GetXIDDocument(GetXIDDocument),
+    ListShares(ListShares),
+    StartRecovery(StartRecovery),
+    StoreShare(StoreShare),
+    StoreShares(StoreShares),
+    UpdateRecovery(UpdateRecovery),
+    #[serde(rename = "updateXIDDocument")]
+    UpdateXIDDocument(UpdateXIDDocument),
+}
+
+impl JsonForm for DepoRequest {
+    type Json = DepoRequestJson;
+
+    fn to_json(&self) -> Self::Json {
+        match self.clone() {
+            Self::DeleteAccount(r) => DepoRequestJson::DeleteAccount(r),
+            Self::DeleteShares(r) => DepoRequestJson::DeleteShares(r),
+            Self::FinishRecovery(r) => DepoRequestJson::FinishRecovery(r),
+            Self::GetRecovery(r) => DepoRequestJson::GetRecovery(r),
+            Self::GetShares(r) => DepoRequestJson::GetShares(r),
+            Self::GetXIDDocument(r) => DepoRequestJson::GetXIDDocument(r),
+            Self::ListShares(r) => DepoRequestJson::ListShares(r),
+            Self::StartRecovery(r) => DepoRequestJson::StartRecovery(r),
+            Self::StoreShare(r) => DepoRequestJson::StoreShare(r),
+            Self::StoreShares(r) => DepoRequestJson::StoreShares(r),
+            Self::UpdateRecovery(r) => DepoRequestJson::UpdateRecovery(r),
+            Self::UpdateXIDDocument(r) => {
+                DepoRequestJson::UpdateXIDDocument(r)
+            }
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(match json {
+            DepoRequestJson::DeleteAccount(r) => Self::DeleteAccount(r),
+            DepoRequestJson::DeleteShares(r) => Self::DeleteShares(r),
+            DepoRequestJson::FinishRecovery(r) => Self::FinishRecovery(r),
+            DepoRequestJson::GetRecovery(r) => Self::GetRecovery(r),
+            DepoRequestJson::GetShares(r) => Self::GetShares(r),
+            DepoRequestJson::GetXIDDocument(r) => Self::GetXIDDocument(r),
+            DepoRequestJson::ListShares(r) => Self::ListShares(r),
+            DepoRequestJson::StartRecovery(r) => Self::StartRecovery(r),
+            DepoRequestJson::StoreShare(r) => Self::StoreShare(r),
+            DepoRequestJson::StoreShares(r) => Self::StoreShares(r),
+            DepoRequestJson::UpdateRecovery(r) => Self::UpdateRecovery(r),
+            DepoRequestJson::UpdateXIDDocument(r) => {
+                Self::UpdateXIDDocument(r)
+            }
+        })
+    }
+}
+impl_serde!(DepoRequest);
+
+//
+// Results
+//
+
+impl JsonForm for OkResult {
+    type Json = EmptyJson;
+
+    fn to_json(&self) -> Self::Json { EmptyJson {} }
+
+    fn from_json(_json: Self::Json) -> Result<Self> { Ok(Self::new()) }
+}
+impl_serde!(OkResult);
+
+impl JsonForm for GetRecoveryResult {
+    type Json = OptionalRecoveryMethodJson;
+
+    fn to_json(&self) -> Self::Json {
+        OptionalRecoveryMethodJson {
+            recovery_method: self.recovery().cloned(),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.recovery_method))
+    }
+}
+impl_serde!(GetRecoveryResult);
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetXIDDocumentResultJson {
+    xid_document: String,
+}
+
+impl JsonForm for GetXIDDocumentResult {
+    type Json = GetXIDDocumentResultJson;
+
+    fn to_json(&self) -> Self::Json {
+        GetXIDDocumentResultJson {
+            xid_document: xid_document_to_ur(self.xid_document()),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(xid_document_from_ur(&json.xid_document)?))
+    }
+}
+impl_serde!(GetXIDDocumentResult);
+
+#[derive(Serialize, Deserialize)]
+struct OptionalContinuationJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    continuation: Option<String>,
+}
+
+impl JsonForm for StartRecoveryResult {
+    type Json = OptionalContinuationJson;
+
+    fn to_json(&self) -> Self::Json {
+        OptionalContinuationJson {
+            continuation: self.continuation().map(envelope_to_ur),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let continuation = json
+            .continuation
+            .map(|continuation| envelope_from_ur(&continuation))
+            .transpose()?;
+        Ok(Self::new(continuation))
+    }
+}
+impl_serde!(StartRecoveryResult);
+
+#[derive(Serialize, Deserialize)]
+struct StoreShareResultJson {
+    receipt: Receipt,
+}
+
+impl JsonForm for StoreShareResult {
+    type Json = StoreShareResultJson;
+
+    fn to_json(&self) -> Self::Json {
+        StoreShareResultJson { receipt: self.receipt().clone() }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.receipt))
+    }
+}
+impl_serde!(StoreShareResult);
+
+impl JsonForm for StoreSharesResult {
+    type Json = ReceiptsJson;
+
+    fn to_json(&self) -> Self::Json {
+        ReceiptsJson { receipts: self.receipts().to_vec() }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new(json.receipts))
+    }
+}
+impl_serde!(StoreSharesResult);
+
+#[derive(Serialize, Deserialize)]
+struct ReceiptShareJson {
+    receipt: Receipt,
+    #[serde(flatten)]
+    share: StoredShare,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetSharesResultJson {
+    shares: Vec<ReceiptShareJson>,
+}
+
+impl JsonForm for GetSharesResult {
+    type Json = GetSharesResultJson;
+
+    fn to_json(&self) -> Self::Json {
+        let shares = self
+            .receipt_to_share()
+            .iter()
+            .map(|(receipt, share)| ReceiptShareJson {
+                receipt: receipt.clone(),
+                share: share.clone(),
+            })
+            .collect();
+        GetSharesResultJson { shares }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        Ok(Self::new_with_shares(
+            json.shares
+                .into_iter()
+                .map(|entry| (entry.receipt, entry.share))
+                .collect(),
+        ))
+    }
+}
+impl_serde!(GetSharesResult);
+
+#[derive(Serialize, Deserialize)]
+struct ReceiptMetadataJson {
+    receipt: Receipt,
+    #[serde(flatten)]
+    metadata: ShareMetadata,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListSharesResultJson {
+    shares: Vec<ReceiptMetadataJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+impl JsonForm for ListSharesResult {
+    type Json = ListSharesResultJson;
+
+    fn to_json(&self) -> Self::Json {
+        let shares = self
+            .receipt_to_metadata()
+            .iter()
+            .map(|(receipt, metadata)| ReceiptMetadataJson {
+                receipt: receipt.clone(),
+                metadata: metadata.clone(),
+            })
+            .collect();
+        ListSharesResultJson {
+            shares,
+            next_cursor: self.next_cursor().map(hex::encode),
+        }
+    }
+
+    fn from_json(json: Self::Json) -> Result<Self> {
+        let receipt_to_metadata = json
+            .shares
+            .into_iter()
+            .map(|entry| (entry.receipt, entry.metadata))
+            .collect();
+        let next_cursor =
+            json.next_cursor.map(|cursor| from_hex(&cursor)).transpose()?;
+        Ok(Self::new(receipt_to_metadata, next_cursor))
+    }
+}
+impl_serde!(ListSharesResult);
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{
+        PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider, XID,
+        XIDProvider,
+    };
+    use bc_rand::make_fake_random_number_generator;
+
+    use super::*;
+
+    fn user_id() -> XID {
+        XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap()
+    }
+
+    fn receipt() -> Receipt { Receipt::new(user_id(), b"data") }
+
+    fn recovery() -> RecoveryMethod {
+        RecoveryMethod::email("alice@example.com").unwrap()
+    }
+
+    /// Checks that `value` survives a trip through JSON with an identical
+    /// envelope.
+    fn assert_round_trip<T>(value: T) -> serde_json::Value
+    where
+        T: Serialize + DeserializeOwned + UREncodable + std::fmt::Debug,
+    {
+        let json = serde_json::to_value(&value).unwrap();
+        let decoded: T = serde_json::from_value(json.clone()).unwrap();
+        assert!(
+            decoded.untagged_cbor() == value.untagged_cbor(),
+            "{:?} decoded from {} as {:?}",
+            value,
+            json,
+            decoded
+        );
+        json
+    }
+
+    #[test]
+    fn test_requests() {
+        bc_envelope::register_tags();
+
+        let metadata = ShareMetadata::new().with_label("Group 1");
+        let json = assert_round_trip(StoreShare::new_with_metadata(
+            b"data", metadata,
+        ));
+        assert_eq!(
+            json,
+            serde_json::json!({"data": "64617461", "label": "Group 1"})
+        );
+        assert_round_trip(StoreShare::new(b"data").with_compression());
+        assert_round_trip(StoreShares::new_from_data([b"data_1", b"data_2"]));
+        assert_round_trip(GetShares::new(vec![receipt()]));
+        assert_round_trip(GetShares::new_all_shares());
+        assert_round_trip(DeleteShares::new(vec![receipt()]));
+        assert_round_trip(DeleteAccount::new());
+        assert_round_trip(GetRecovery::new());
+        assert_round_trip(ListShares::new().with_limit(10).with_cursor(b"c"));
+        assert_round_trip(StartRecovery::new(recovery()));
+        assert_round_trip(UpdateRecovery::new(Some(recovery())));
+        assert_round_trip(UpdateRecovery::new(None));
+        assert_round_trip(FinishRecovery::new(Envelope::new("continuation")));
+
+        let mut rng = make_fake_random_number_generator();
+        let xid_document: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        assert_round_trip(UpdateXIDDocument::new(xid_document.clone()));
+        let new_keys = PrivateKeyBase::new_using(&mut rng);
+        let new_xid_document: XIDDocument = new_keys.public_keys().into();
+        let json = assert_round_trip(
+            UpdateXIDDocument::new(new_xid_document.clone())
+                .with_previous_xid_document(&xid_document)
+                .with_proof(&new_xid_document.xid(), &new_keys.private_keys())
+                .unwrap(),
+        );
+        assert!(json["previousDigest"].is_string());
+        assert!(json["proof"].is_string());
+        assert_round_trip(GetXIDDocument::new());
+    }
+
+    #[test]
+    fn test_depo_request() {
+        bc_envelope::register_tags();
+
+        let json = assert_round_trip(DepoRequest::GetShares(GetShares::new(
+            vec![receipt()],
+        )));
+        assert_eq!(json["function"], "getShares");
+        assert_eq!(json["receipts"][0], receipt().ur_string());
+
+        let json = serde_json::json!({
+            "function": "startRecovery",
+            "recoveryMethod": {"kind": "email", "value": "alice@example.com"}
+        });
+        let request: DepoRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            request,
+            DepoRequest::StartRecovery(StartRecovery::new(recovery()))
+        );
+
+        let json = serde_json::json!({"function": "storeShare", "data": "xx"});
+        assert!(serde_json::from_value::<DepoRequest>(json).is_err());
+    }
+
+    #[test]
+    fn test_results() {
+        bc_envelope::register_tags();
+
+        assert_round_trip(OkResult::new());
+        assert_round_trip(GetRecoveryResult::new(Some(recovery())));
+        assert_round_trip(StartRecoveryResult::new(None));
+        assert_round_trip(StartRecoveryResult::new(Some(
+            Envelope::new("continuation").wrap(),
+        )));
+        assert_round_trip(StoreShareResult::new(receipt()));
+        assert_round_trip(StoreSharesResult::new(vec![receipt()]));
+
+        let mut rng = make_fake_random_number_generator();
+        let xid_document: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        assert_round_trip(GetXIDDocumentResult::new(xid_document));
+
+        let salt = Salt::from_data(b"saltsalt");
+        let metadata = ShareMetadata::new()
+            .with_stored_at(Date::from_timestamp(1_000_000.0))
+            .with_size(4)
+            .with_salt(salt.clone());
+        let salted = Receipt::new_salted(user_id(), &salt, b"data");
+        let share = StoredShare::new_with_metadata(b"data", metadata.clone());
+        let json = assert_round_trip(GetSharesResult::new_with_shares(
+            vec![(receipt(), share.clone()), (salted.clone(), share)]
+                .into_iter()
+                .collect(),
+        ));
+        assert_eq!(json["shares"].as_array().unwrap().len(), 2);
+
+        let json = assert_round_trip(ListSharesResult::new(
+            vec![(salted, metadata)].into_iter().collect(),
+            Some(ByteString::from(b"cursor")),
+        ));
+        assert_eq!(json["shares"][0]["storedAt"], 1_000_000.0);
+        assert_eq!(json["nextCursor"], "637572736f72");
+    }
+}
@@ -1,8 +1,56 @@
-mod error;
-use error::{Error, Result};
+pub mod error;
+pub use error::{Error, Result};
+
+pub mod depo_error;
+pub use depo_error::DepoError;
+
+pub mod client;
+pub use client::{DepoClient, InProcessTransport, Transport};
+
+pub mod server;
+pub use server::{DepoHandler, Sender, dispatch, dispatch_checked};
+
+pub mod encrypted_share;
+pub use encrypted_share::EncryptedShare;
+
+pub mod freshness;
+pub use freshness::{FreshnessPolicy, MemoryReplayCache, ReplayCache};
+
+#[cfg(feature = "memory-depo")]
+pub mod memory_depo;
+#[cfg(feature = "memory-depo")]
+pub use memory_depo::MemoryDepo;
+
+#[cfg(feature = "serde")]
+pub mod json;
+
+pub mod permissions;
+pub use permissions::{check_permission, required_privilege};
 
 pub mod receipt;
-pub use receipt::Receipt;
+pub use receipt::{Receipt, ReceiptVersion};
+
+pub mod recovery_continuation;
+pub use recovery_continuation::RecoveryContinuation;
+
+pub mod recovery_manifest;
+pub use recovery_manifest::{
+    GroupLayout, RecoveryManifest, SealedRecoveryManifest, ShareLayout,
+};
+
+pub mod recovery_method;
+pub use recovery_method::RecoveryMethod;
+
+pub mod share_distribution;
+pub use share_distribution::{
+    DepoEndpoint, PlannedShare, RecoveryPlan, ShareDistribution,
+};
+
+pub mod share_metadata;
+pub use share_metadata::{ShareMetadata, StoredShare};
+
+pub mod ur;
+pub use ur::{from_ur_parts, register_tags, register_tags_in, to_ur_parts};
 
 pub mod request;
 use bc_envelope::prelude::*;
@@ -30,6 +78,14 @@ pub const GET_SHARES_FUNCTION_NAME: &str = "getShares";
 pub const GET_SHARES_FUNCTION: Function =
     Function::new_static_named(GET_SHARES_FUNCTION_NAME);
 
+pub const GET_XID_DOCUMENT_FUNCTION_NAME: &str = "getXIDDocument";
+pub const GET_XID_DOCUMENT_FUNCTION: Function =
+    Function::new_static_named(GET_XID_DOCUMENT_FUNCTION_NAME);
+
+pub const LIST_SHARES_FUNCTION_NAME: &str = "listShares";
+pub const LIST_SHARES_FUNCTION: Function =
+    Function::new_static_named(LIST_SHARES_FUNCTION_NAME);
+
 pub const START_RECOVERY_FUNCTION_NAME: &str = "startRecovery";
 pub const START_RECOVERY_FUNCTION: Function =
     Function::new_static_named(START_RECOVERY_FUNCTION_NAME);
@@ -38,6 +94,10 @@ pub const STORE_SHARE_FUNCTION_NAME: &str = "storeShare";
 pub const STORE_SHARE_FUNCTION: Function =
     Function::new_static_named(STORE_SHARE_FUNCTION_NAME);
 
+pub const STORE_SHARES_FUNCTION_NAME: &str = "storeShares";
+pub const STORE_SHARES_FUNCTION: Function =
+    Function::new_static_named(STORE_SHARES_FUNCTION_NAME);
+
 pub const UPDATE_XID_DOCUMENT_FUNCTION_NAME: &str = "updateXIDDocument";
 pub const UPDATE_XID_DOCUMENT_FUNCTION: Function =
     Function::new_static_named(UPDATE_XID_DOCUMENT_FUNCTION_NAME);
@@ -48,18 +108,52 @@ pub const UPDATE_RECOVERY_FUNCTION: Function =
 
 // Parameters
 
+pub const APPLICATION_TAG_PARAM_NAME: &str = "applicationTag";
+pub const APPLICATION_TAG_PARAM: Parameter =
+    Parameter::new_static_named(APPLICATION_TAG_PARAM_NAME);
+
+pub const CONTENT_TYPE_PARAM_NAME: &str = "contentType";
+pub const CONTENT_TYPE_PARAM: Parameter =
+    Parameter::new_static_named(CONTENT_TYPE_PARAM_NAME);
+
+pub const CURSOR_PARAM_NAME: &str = "cursor";
+pub const CURSOR_PARAM: Parameter =
+    Parameter::new_static_named(CURSOR_PARAM_NAME);
+
 pub const DATA_PARAM_NAME: &str = "data";
 pub const DATA_PARAM: Parameter = Parameter::new_static_named(DATA_PARAM_NAME);
 
+pub const LABEL_PARAM_NAME: &str = "label";
+pub const LABEL_PARAM: Parameter =
+    Parameter::new_static_named(LABEL_PARAM_NAME);
+
+pub const LIMIT_PARAM_NAME: &str = "limit";
+pub const LIMIT_PARAM: Parameter =
+    Parameter::new_static_named(LIMIT_PARAM_NAME);
+
 pub const NEW_XID_DOCUMENT_PARAM_NAME: &str = "newXIDDocument";
 pub const NEW_XID_DOCUMENT_PARAM: Parameter =
     Parameter::new_static_named(NEW_XID_DOCUMENT_PARAM_NAME);
 
+pub const PREVIOUS_DIGEST_PARAM_NAME: &str = "previousDigest";
+pub const PREVIOUS_DIGEST_PARAM: Parameter =
+    Parameter::new_static_named(PREVIOUS_DIGEST_PARAM_NAME);
+
+pub const PROOF_PARAM_NAME: &str = "proof";
+pub const PROOF_PARAM: Parameter =
+    Parameter::new_static_named(PROOF_PARAM_NAME);
+
 pub const RECEIPT_PARAM_NAME: &str = "receipt";
 pub const RECEIPT_PARAM: Parameter =
     Parameter::new_static_named(RECEIPT_PARAM_NAME);
 
+pub const SHARE_PARAM_NAME: &str = "share";
+pub const SHARE_PARAM: Parameter =
+    Parameter::new_static_named(SHARE_PARAM_NAME);
+
 pub const RECOVERY_CONTINUATION_PARAM_NAME: &str = "recoveryContinuation";
+pub const RECOVERY_CONTINUATION_PARAM: Parameter =
+    Parameter::new_static_named(RECOVERY_CONTINUATION_PARAM_NAME);
 
 pub const RECOVERY_METHOD_PARAM_NAME: &str = "recoveryMethod";
 pub const RECOVERY_METHOD_PARAM: Parameter =
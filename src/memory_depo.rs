@@ -0,0 +1,1125 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use bc_components::{
+    ARID, PrivateKeyBase, PrivateKeys, PrivateKeysProvider, PublicKeysProvider,
+    Salt, XID, XIDProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    DELETE_ACCOUNT_FUNCTION, DELETE_SHARES_FUNCTION, DeleteAccount,
+    DeleteShares, DepoError, DepoHandler, Error, FinishRecovery,
+    GET_RECOVERY_FUNCTION, GET_SHARES_FUNCTION, GET_XID_DOCUMENT_FUNCTION,
+    GetRecovery, GetRecoveryResult, GetShares, GetSharesResult, GetXIDDocument,
+    GetXIDDocumentResult, InProcessTransport, LIST_SHARES_FUNCTION, ListShares,
+    ListSharesResult, OkResult, Receipt, RecoveryContinuation, RecoveryMethod,
+    Result, STORE_SHARE_FUNCTION, STORE_SHARES_FUNCTION, Sender, StartRecovery,
+    StartRecoveryResult, StoreShare, StoreShareResult, StoreShares,
+    StoreSharesResult, StoredShare, UPDATE_RECOVERY_FUNCTION,
+    UPDATE_XID_DOCUMENT_FUNCTION, UpdateRecovery, UpdateXIDDocument,
+    check_permission, dispatch,
+};
+
+/// How long, in seconds, a recovery continuation issued by the depo remains
+/// valid.
+pub const RECOVERY_CONTINUATION_LIFETIME: f64 = 24.0 * 60.0 * 60.0;
+
+/// The most shares returned by a single `listShares` request.
+pub const MAX_LIST_SHARES_LIMIT: usize = 100;
+
+#[derive(Debug, Clone)]
+struct Account {
+    xid_document: XIDDocument,
+    shares: HashMap<Receipt, StoredShare>,
+    recovery: Option<RecoveryMethod>,
+}
+
+/// A depo that keeps everything in memory, for testing clients against
+/// real depo semantics without a server or database.
+///
+/// Accounts are keyed by the `XID` of the user who first stores a share.
+/// Requests are attributed to an account through the `XID` of the document
+/// currently controlling it, and must be signed by a key of that document as
+/// the depo holds it, so keys dropped by `updateXIDDocument` lose access at
+/// once. That key must also hold the privilege `required_privilege` names
+/// for the function called. Recovering the account hands it, and its XID
+/// document, to the recovering keys, which must not already hold an account,
+/// and cuts off the keys it was taken from.
+///
+/// Recovery continuations are signed and sealed with keys the depo generates
+/// for itself, and are "delivered" by holding them until the test collects
+/// them with `take_delivered_continuation`.
+///
+/// Shares sent compressed are returned compressed, though their receipts
+/// are computed over the uncompressed data.
+///
+/// Receipts are unsalted unless the depo is created `with_salted_receipts`.
+/// Shares stored before the switch keep their unsalted receipts.
+#[derive(Clone)]
+pub struct MemoryDepo {
+    keys: PrivateKeyBase,
+    salted_receipts: bool,
+    accounts: HashMap<XID, Account>,
+    controllers: HashMap<XID, XID>,
+    used_continuations: HashSet<ARID>,
+    delivered_continuations: HashMap<RecoveryMethod, Envelope>,
+}
+
+impl MemoryDepo {
+    pub fn new() -> Self {
+        Self {
+            keys: PrivateKeyBase::new(),
+            salted_receipts: false,
+            accounts: HashMap::new(),
+            controllers: HashMap::new(),
+            used_continuations: HashSet::new(),
+            delivered_continuations: HashMap::new(),
+        }
+    }
+
+    /// Issues salted receipts for shares stored from now on.
+    pub fn with_salted_receipts(mut self) -> Self {
+        self.salted_receipts = true;
+        self
+    }
+
+    /// Returns a transport that dispatches sealed requests to `depo`, which
+    /// answers as `xid_document` using `private_keys`.
+    pub fn transport(
+        depo: Rc<RefCell<Self>>,
+        private_keys: PrivateKeys,
+        xid_document: XIDDocument,
+    ) -> InProcessTransport {
+        InProcessTransport::new(move |envelope| {
+            dispatch(
+                &mut *depo.borrow_mut(),
+                &envelope,
+                &private_keys,
+                &xid_document,
+            )
+        })
+    }
+
+    pub fn account_count(&self) -> usize { self.accounts.len() }
+
+    pub fn share_count(&self) -> usize {
+        self.accounts
+            .values()
+            .map(|account| account.shares.len())
+            .sum()
+    }
+
+    /// Removes and returns the continuation most recently sent to
+    /// `recovery`, standing in for the out-of-band channel, such as email, a
+    /// real depo would use.
+    pub fn take_delivered_continuation(
+        &mut self,
+        recovery: &RecoveryMethod,
+    ) -> Option<Envelope> {
+        self.delivered_continuations.remove(recovery)
+    }
+
+    /// The XID document stored for the account identified by `id`.
+    pub fn xid_document(&self, id: &XID) -> Option<&XIDDocument> {
+        self.accounts.get(id).map(|account| &account.xid_document)
+    }
+
+    /// The account `sender` controls. The XID it claims must control an
+    /// account, and the request must be signed by a key of that account's
+    /// XID document, so a document naming someone else's XID reaches no
+    /// account.
+    fn account_id(&self, sender: &Sender) -> Option<XID> {
+        let id = self.controllers.get(&sender.claimed_xid()).copied()?;
+        let account = self.accounts.get(&id)?;
+        sender.is_key_of(&account.xid_document).then_some(id)
+    }
+
+    /// The account `sender` controls, provided the key that signed the
+    /// request may call `function` there.
+    fn permitted_account_id(
+        &self,
+        sender: &Sender,
+        function: &Function,
+    ) -> Result<XID> {
+        let id = self
+            .account_id(sender)
+            .ok_or(Error::Remote(DepoError::UnknownAccount))?;
+        check_permission(
+            &self.accounts[&id].xid_document,
+            sender.signing_key(),
+            function,
+        )?;
+        Ok(id)
+    }
+
+    fn account(
+        &self,
+        sender: &Sender,
+        function: &Function,
+    ) -> Result<&Account> {
+        let id = self.permitted_account_id(sender, function)?;
+        Ok(&self.accounts[&id])
+    }
+
+    fn account_mut(
+        &mut self,
+        sender: &Sender,
+        function: &Function,
+    ) -> Result<&mut Account> {
+        let id = self.permitted_account_id(sender, function)?;
+        Ok(self.accounts.get_mut(&id).unwrap())
+    }
+
+    /// The account `sender` stores shares in with `function`, which is
+    /// created by its first share. Only the inception key of an XID can
+    /// create its account.
+    fn account_id_for_storing(
+        &mut self,
+        sender: &Sender,
+        function: &Function,
+    ) -> Result<XID> {
+        if self.account_id(sender).is_some() {
+            return self.permitted_account_id(sender, function);
+        }
+        let id = sender.claimed_xid();
+        if !sender.is_self_certified() || self.controllers.contains_key(&id) {
+            return Err(Error::Remote(DepoError::UnknownAccount));
+        }
+        check_permission(
+            sender.xid_document(),
+            sender.signing_key(),
+            function,
+        )?;
+        if self.accounts.contains_key(&id) {
+            return Err(Error::Remote(DepoError::AccountRecovered));
+        }
+        self.accounts.insert(id, Account {
+            xid_document: sender.xid_document().clone(),
+            shares: HashMap::new(),
+            recovery: None,
+        });
+        self.controllers.insert(id, id);
+        Ok(id)
+    }
+
+    fn insert_share(&mut self, id: XID, request: &StoreShare) -> Receipt {
+        let mut metadata = request
+            .metadata()
+            .clone()
+            .with_stored_at(Date::now())
+            .with_size(request.data().len());
+        let receipt = if self.salted_receipts {
+            let salt = Salt::new_for_size(request.data().len());
+            let receipt = Receipt::new_salted(id, &salt, request.data());
+            metadata = metadata.with_salt(salt);
+            receipt
+        } else {
+            Receipt::new(id, request.data())
+        };
+        let mut share =
+            StoredShare::new_with_metadata(request.data(), metadata);
+        if request.is_compressed() {
+            share = share.with_compression();
+        }
+        self.accounts
+            .get_mut(&id)
+            .unwrap()
+            .shares
+            .insert(receipt.clone(), share);
+        receipt
+    }
+
+    fn account_with_recovery(&self, recovery: &RecoveryMethod) -> Option<XID> {
+        self.accounts
+            .iter()
+            .find(|(_, account)| account.recovery.as_ref() == Some(recovery))
+            .map(|(id, _)| *id)
+    }
+}
+
+impl Default for MemoryDepo {
+    fn default() -> Self { Self::new() }
+}
+
+fn invalid_request(message: &str) -> Error {
+    Error::Remote(DepoError::InvalidRequest(message.to_string()))
+}
+
+/// Selects the shares named by `receipts`, or every share if it is empty.
+fn selected_receipts(
+    shares: &HashMap<Receipt, StoredShare>,
+    receipts: &HashSet<Receipt>,
+) -> Vec<Receipt> {
+    if receipts.is_empty() {
+        shares.keys().cloned().collect()
+    } else {
+        receipts
+            .iter()
+            .filter(|receipt| shares.contains_key(receipt))
+            .cloned()
+            .collect()
+    }
+}
+
+impl DepoHandler for MemoryDepo {
+    fn store_share(
+        &mut self,
+        sender: &Sender,
+        request: StoreShare,
+    ) -> Result<StoreShareResult> {
+        let id = self.account_id_for_storing(sender, &STORE_SHARE_FUNCTION)?;
+        Ok(StoreShareResult::new(self.insert_share(id, &request)))
+    }
+
+    /// Shares in a batch can't fail individually, so once the account is
+    /// known the whole batch is stored.
+    fn store_shares(
+        &mut self,
+        sender: &Sender,
+        request: StoreShares,
+    ) -> Result<StoreSharesResult> {
+        let id =
+            self.account_id_for_storing(sender, &STORE_SHARES_FUNCTION)?;
+        let receipts = request
+            .shares()
+            .iter()
+            .map(|share| self.insert_share(id, share))
+            .collect();
+        Ok(StoreSharesResult::new(receipts))
+    }
+
+    fn get_shares(
+        &mut self,
+        sender: &Sender,
+        request: GetShares,
+    ) -> Result<GetSharesResult> {
+        let account = self.account(sender, &GET_SHARES_FUNCTION)?;
+        let receipt_to_share =
+            selected_receipts(&account.shares, request.receipts())
+                .into_iter()
+                .map(|receipt| {
+                    let share = account.shares[&receipt].clone();
+                    (receipt, share)
+                })
+                .collect();
+        Ok(GetSharesResult::new_with_shares(receipt_to_share))
+    }
+
+    /// Lists shares in order of their receipts. The cursor is the last
+    /// receipt of the previous page.
+    fn list_shares(
+        &mut self,
+        sender: &Sender,
+        request: ListShares,
+    ) -> Result<ListSharesResult> {
+        let account = self.account(sender, &LIST_SHARES_FUNCTION)?;
+        let limit = request
+            .limit()
+            .unwrap_or(MAX_LIST_SHARES_LIMIT)
+            .min(MAX_LIST_SHARES_LIMIT);
+        if limit == 0 {
+            return Err(invalid_request("limit must be positive"));
+        }
+        let mut receipts: Vec<&Receipt> = account
+            .shares
+            .keys()
+            .filter(|receipt| match request.cursor() {
+                Some(cursor) => receipt.data().as_slice() > cursor.data(),
+                None => true,
+            })
+            .collect();
+        receipts.sort_by_key(|receipt| receipt.data());
+        let next_cursor = (receipts.len() > limit)
+            .then(|| ByteString::from(receipts[limit - 1].data().to_vec()));
+        let receipt_to_metadata = receipts
+            .into_iter()
+            .take(limit)
+            .map(|receipt| {
+                let metadata = account.shares[receipt].metadata().clone();
+                (receipt.clone(), metadata)
+            })
+            .collect();
+        Ok(ListSharesResult::new(receipt_to_metadata, next_cursor))
+    }
+
+    fn delete_shares(
+        &mut self,
+        sender: &Sender,
+        request: DeleteShares,
+    ) -> Result<OkResult> {
+        let account = self.account_mut(sender, &DELETE_SHARES_FUNCTION)?;
+        for receipt in selected_receipts(&account.shares, request.receipts()) {
+            account.shares.remove(&receipt);
+        }
+        Ok(OkResult::new())
+    }
+
+    fn update_xid_document(
+        &mut self,
+        sender: &Sender,
+        request: UpdateXIDDocument,
+    ) -> Result<OkResult> {
+        let account = self.account_mut(sender, &UPDATE_XID_DOCUMENT_FUNCTION)?;
+        if request.new_xid_document().xid() != account.xid_document.xid() {
+            return Err(invalid_request(
+                "new XID document is for a different XID",
+            ));
+        }
+        request.validate(&account.xid_document, false)?;
+        account.xid_document = request.new_xid_document().clone();
+        Ok(OkResult::new())
+    }
+
+    fn get_xid_document(
+        &mut self,
+        sender: &Sender,
+        _request: GetXIDDocument,
+    ) -> Result<GetXIDDocumentResult> {
+        let xid_document = self
+            .account(sender, &GET_XID_DOCUMENT_FUNCTION)?
+            .xid_document
+            .clone();
+        Ok(GetXIDDocumentResult::new(xid_document))
+    }
+
+    fn update_recovery(
+        &mut self,
+        sender: &Sender,
+        request: UpdateRecovery,
+    ) -> Result<OkResult> {
+        let id =
+            self.permitted_account_id(sender, &UPDATE_RECOVERY_FUNCTION)?;
+        let in_use = request
+            .recovery()
+            .and_then(|recovery| self.account_with_recovery(recovery))
+            .is_some_and(|other| other != id);
+        if in_use {
+            return Err(Error::Remote(DepoError::RecoveryMethodInUse));
+        }
+        self.accounts.get_mut(&id).unwrap().recovery =
+            request.recovery().cloned();
+        Ok(OkResult::new())
+    }
+
+    fn get_recovery(
+        &mut self,
+        sender: &Sender,
+        _request: GetRecovery,
+    ) -> Result<GetRecoveryResult> {
+        let account = self.account(sender, &GET_RECOVERY_FUNCTION)?;
+        Ok(GetRecoveryResult::new(account.recovery.clone()))
+    }
+
+    fn start_recovery(
+        &mut self,
+        sender: &Sender,
+        request: StartRecovery,
+    ) -> Result<StartRecoveryResult> {
+        let id = self
+            .account_with_recovery(request.recovery())
+            .ok_or(Error::Remote(DepoError::UnknownRecoveryMethod))?;
+        let new_public_keys = sender
+            .xid_document()
+            .inception_key()
+            .filter(|_| sender.is_self_certified())
+            .map(|key| key.public_keys().clone())
+            .ok_or_else(|| {
+                invalid_request("request not signed by the inception key")
+            })?;
+        let expiry = Date::from_timestamp(
+            Date::now().timestamp() + RECOVERY_CONTINUATION_LIFETIME,
+        );
+        let continuation =
+            RecoveryContinuation::new(id, new_public_keys, expiry).seal(
+                &self.keys.private_keys(),
+                &self.keys.public_keys(),
+            );
+        self.delivered_continuations
+            .insert(request.recovery().clone(), continuation);
+        Ok(StartRecoveryResult::new(None))
+    }
+
+    fn finish_recovery(
+        &mut self,
+        sender: &Sender,
+        request: FinishRecovery,
+    ) -> Result<OkResult> {
+        let continuation = RecoveryContinuation::unseal(
+            request.continuation(),
+            &self.keys.public_keys(),
+            &self.keys.private_keys(),
+        )
+        .map_err(|_| Error::Remote(DepoError::InvalidContinuation))?;
+        if continuation.is_expired(&Date::now()) {
+            return Err(Error::Remote(DepoError::RecoveryExpired));
+        }
+        if !sender.is_self_certified()
+            || !continuation.is_for(sender.xid_document())
+        {
+            return Err(Error::Remote(DepoError::InvalidContinuation));
+        }
+        let id = continuation.xid();
+        if !self.accounts.contains_key(&id) {
+            return Err(Error::Remote(DepoError::UnknownAccount));
+        }
+        // Taking over the account must not cut the sender off from an
+        // account of its own.
+        if self.accounts.contains_key(&sender.claimed_xid())
+            || self.account_id(sender).is_some_and(|other| other != id)
+        {
+            return Err(invalid_request("sender already has an account"));
+        }
+        if !self.used_continuations.insert(continuation.nonce()) {
+            return Err(Error::Remote(DepoError::ContinuationUsed));
+        }
+        self.controllers.retain(|_, account_id| *account_id != id);
+        self.controllers.insert(sender.claimed_xid(), id);
+        self.accounts.get_mut(&id).unwrap().xid_document =
+            sender.xid_document().clone();
+        Ok(OkResult::new())
+    }
+
+    fn delete_account(
+        &mut self,
+        sender: &Sender,
+        _request: DeleteAccount,
+    ) -> Result<OkResult> {
+        let id =
+            self.permitted_account_id(sender, &DELETE_ACCOUNT_FUNCTION)?;
+        self.accounts.remove(&id);
+        self.controllers.retain(|_, account_id| *account_id != id);
+        Ok(OkResult::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_rand::make_fake_random_number_generator;
+
+    use bc_components::{SSKRGroupSpec, SSKRSecret, SSKRSpec};
+    use bc_xid::{Key, Privilege};
+
+    use super::*;
+    use crate::{DepoClient, DepoEndpoint, ShareDistribution, ShareMetadata};
+
+    fn remote_error<T>(result: Result<T>) -> DepoError {
+        match result {
+            Err(Error::Remote(error)) => error,
+            Err(error) => panic!("expected a depo error, got {}", error),
+            Ok(_) => panic!("expected a depo error"),
+        }
+    }
+
+    struct Fixture {
+        depo: Rc<RefCell<MemoryDepo>>,
+        depo_keys: PrivateKeyBase,
+    }
+
+    impl Fixture {
+        fn new(depo_keys: PrivateKeyBase) -> Self {
+            Self { depo: Rc::new(RefCell::new(MemoryDepo::new())), depo_keys }
+        }
+
+        fn client(&self, user_keys: &PrivateKeyBase) -> DepoClient {
+            self.client_with_document(
+                user_keys.private_keys(),
+                user_keys.public_keys().into(),
+            )
+        }
+
+        /// A client that sends `xid_document` with its requests and signs
+        /// them with `private_keys`.
+        fn client_with_document(
+            &self,
+            private_keys: PrivateKeys,
+            xid_document: XIDDocument,
+        ) -> DepoClient {
+            let depo_xid_document: XIDDocument =
+                self.depo_keys.public_keys().into();
+            let transport = MemoryDepo::transport(
+                self.depo.clone(),
+                self.depo_keys.private_keys(),
+                depo_xid_document.clone(),
+            );
+            DepoClient::new(
+                private_keys,
+                xid_document,
+                depo_xid_document,
+                transport,
+            )
+        }
+    }
+
+    #[test]
+    fn test_shares() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        let bob = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+
+        // Accounts don't exist until the first share is stored.
+        assert_eq!(
+            remote_error(alice.get_all_shares()),
+            DepoError::UnknownAccount
+        );
+
+        let receipt_1 = alice.store_share(b"data_1").unwrap();
+        let receipt_2 = alice.store_share(b"data_2").unwrap();
+        let receipt_3 = bob.store_share(b"data_1").unwrap();
+        assert_eq!(fixture.depo.borrow().account_count(), 2);
+        assert_eq!(fixture.depo.borrow().share_count(), 3);
+
+        // Receipts depend on the account as well as the data.
+        assert_ne!(receipt_1, receipt_3);
+
+        let result = alice.get_shares(vec![receipt_1.clone()]).unwrap();
+        assert_eq!(result.receipt_to_data().len(), 1);
+        assert_eq!(
+            result.data_for_receipt(&receipt_1).unwrap().data(),
+            b"data_1"
+        );
+
+        let result = alice.get_all_shares().unwrap();
+        assert_eq!(result.receipt_to_data().len(), 2);
+        assert_eq!(
+            result.data_for_receipt(&receipt_2).unwrap().data(),
+            b"data_2"
+        );
+
+        // One account can't see another's shares.
+        let result = bob.get_shares(vec![receipt_1.clone()]).unwrap();
+        assert!(result.receipt_to_data().is_empty());
+
+        alice.delete_shares(vec![receipt_1.clone()]).unwrap();
+        let result = alice.get_all_shares().unwrap();
+        assert_eq!(result.receipt_to_data().len(), 1);
+        assert!(result.data_for_receipt(&receipt_2).is_some());
+
+        alice.delete_account().unwrap();
+        assert!(alice.get_all_shares().is_err());
+        assert_eq!(fixture.depo.borrow().account_count(), 1);
+        assert_eq!(fixture.depo.borrow().share_count(), 1);
+    }
+
+    #[test]
+    fn test_forged_sender() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        alice.store_share(b"data").unwrap();
+
+        // Mallory sends a document claiming Alice's XID, listing Mallory's
+        // own key, and signs with that key.
+        let mallory_keys = PrivateKeyBase::new_using(&mut rng);
+        let mut forged = XIDDocument::from_xid(alice.xid_document().xid());
+        forged
+            .add_key(Key::new_allow_all(mallory_keys.public_keys()))
+            .unwrap();
+        let mallory =
+            fixture.client_with_document(mallory_keys.private_keys(), forged);
+        assert_eq!(
+            remote_error(mallory.get_all_shares()),
+            DepoError::UnknownAccount
+        );
+        assert_eq!(
+            remote_error(mallory.store_share(b"forged")),
+            DepoError::UnknownAccount
+        );
+        assert_eq!(
+            remote_error(mallory.delete_account()),
+            DepoError::UnknownAccount
+        );
+
+        // Nor can a forged document open an account for an unused XID.
+        let unused = PrivateKeyBase::new_using(&mut rng);
+        let mut forged = XIDDocument::from(unused.public_keys());
+        forged
+            .add_key(Key::new_allow_all(mallory_keys.public_keys()))
+            .unwrap();
+        forged.remove_inception_key().unwrap();
+        let mallory =
+            fixture.client_with_document(mallory_keys.private_keys(), forged);
+        assert_eq!(
+            remote_error(mallory.store_share(b"forged")),
+            DepoError::UnknownAccount
+        );
+
+        assert_eq!(fixture.depo.borrow().account_count(), 1);
+        assert_eq!(fixture.depo.borrow().share_count(), 1);
+        assert_eq!(alice.get_all_shares().unwrap().receipt_to_data().len(), 1);
+    }
+
+    #[test]
+    fn test_share_metadata() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+
+        let before = Date::now();
+        let metadata = ShareMetadata::new()
+            .with_label("Group 1")
+            .with_application_tag("wallet");
+        let receipt =
+            alice.store_share_with_metadata(b"data", metadata).unwrap();
+
+        let result = alice.get_shares(vec![receipt.clone()]).unwrap();
+        let metadata = result.share_for_receipt(&receipt).unwrap().metadata();
+        assert_eq!(metadata.label(), Some("Group 1"));
+        assert_eq!(metadata.content_type(), None);
+        assert_eq!(metadata.application_tag(), Some("wallet"));
+        assert_eq!(metadata.size(), Some(4));
+        assert!(metadata.stored_at().unwrap() >= &before);
+    }
+
+    #[test]
+    fn test_store_shares() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+
+        let data = [b"data_1", b"data_2", b"data_3"];
+        let receipts = alice.store_shares(data).unwrap();
+        assert_eq!(receipts.len(), 3);
+        assert_eq!(fixture.depo.borrow().share_count(), 3);
+        let user_id = alice.xid_document().xid();
+        for (receipt, data) in receipts.iter().zip(data) {
+            assert_eq!(receipt, &Receipt::new(user_id, data));
+        }
+
+        let result = alice.get_shares(receipts).unwrap();
+        assert_eq!(result.receipt_to_data().len(), 3);
+    }
+
+    #[test]
+    fn test_compressed_shares() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        let user_id = alice.xid_document().xid();
+
+        let data = "descriptor ".repeat(100).into_bytes();
+        let request = StoreShare::new(data.clone()).with_compression();
+        let receipt = alice.send(request).unwrap().receipt().clone();
+
+        // Receipts are stable whether or not the share was compressed.
+        assert_eq!(receipt, Receipt::new(user_id, &data));
+
+        let result = alice.get_shares(vec![receipt.clone()]).unwrap();
+        let share = result.share_for_receipt(&receipt).unwrap();
+        assert!(share.is_compressed());
+        assert_eq!(share.data().data(), data.as_slice());
+        assert_eq!(share.metadata().size(), Some(data.len()));
+    }
+
+    #[test]
+    fn test_share_distribution() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixtures = (0..3)
+            .map(|_| Fixture::new(PrivateKeyBase::new_using(&mut rng)))
+            .collect::<Vec<_>>();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let clients = fixtures
+            .iter()
+            .map(|fixture| fixture.client(&user_keys))
+            .collect::<Vec<_>>();
+        let user_id = clients[0].xid_document().xid();
+        let endpoints = clients
+            .iter()
+            .enumerate()
+            .map(|(i, client)| {
+                DepoEndpoint::new(
+                    format!("depo-{}", i),
+                    client.depo_xid_document().clone(),
+                )
+            })
+            .collect();
+
+        let secret = SSKRSecret::new(b"my secret belongs to me.").unwrap();
+        let group = SSKRGroupSpec::new(2, 3).unwrap();
+        let spec = SSKRSpec::new(1, vec![group]).unwrap();
+        let distribution =
+            ShareDistribution::new(&secret, &spec, endpoints).unwrap();
+
+        // Clients out of the order of the endpoints are refused before
+        // anything is stored.
+        let swapped = [1, 0, 2].map(|i| fixtures[i].client(&user_keys));
+        assert!(matches!(
+            distribution.store(&swapped),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+        for fixture in &fixtures {
+            assert_eq!(fixture.depo.borrow().share_count(), 0);
+        }
+
+        let plan = distribution.store(&clients).unwrap();
+        for fixture in &fixtures {
+            assert_eq!(fixture.depo.borrow().share_count(), 1);
+        }
+        assert!(matches!(
+            plan.retrieve(&user_id, &swapped),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+
+        // Losing one depo still leaves enough shares.
+        clients[0].delete_account().unwrap();
+        assert_eq!(plan.retrieve(&user_id, &clients).unwrap(), Some(secret));
+
+        clients[1].delete_account().unwrap();
+        assert_eq!(plan.retrieve(&user_id, &clients).unwrap(), None);
+    }
+
+    #[test]
+    fn test_salted_receipts() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        let user_id = alice.xid_document().xid();
+        let alice = alice.with_share_verification(user_id);
+
+        let receipt_1 = alice.store_share(b"data").unwrap();
+        assert!(!receipt_1.is_salted());
+
+        // Shares stored before the switch keep their unsalted receipts.
+        let depo = fixture.depo.borrow().clone().with_salted_receipts();
+        *fixture.depo.borrow_mut() = depo;
+        let receipt_2 = alice.store_share(b"data").unwrap();
+        let receipt_3 = alice.store_share(b"data").unwrap();
+        assert!(receipt_2.is_salted());
+        assert!(!receipt_2.verify(&user_id, b"data"));
+
+        // Identical data no longer yields identical receipts.
+        assert_ne!(receipt_2, receipt_3);
+        assert_eq!(fixture.depo.borrow().share_count(), 3);
+
+        let result = alice.get_all_shares().unwrap();
+        assert_eq!(result.receipt_to_data().len(), 3);
+        let share = result.share_for_receipt(&receipt_2).unwrap();
+        assert!(share.metadata().salt().is_some());
+        assert!(
+            result
+                .share_for_receipt(&receipt_1)
+                .unwrap()
+                .metadata()
+                .salt()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_list_shares() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+
+        let receipts: HashSet<Receipt> = (0..5)
+            .map(|i| format!("data_{}", i).into_bytes())
+            .map(|data| alice.store_share(data).unwrap())
+            .collect();
+
+        let mut listed = HashSet::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let result = alice.list_shares(Some(2), cursor).unwrap();
+            assert!(result.receipt_to_metadata().len() <= 2);
+            for (receipt, metadata) in result.receipt_to_metadata() {
+                assert_eq!(metadata.size(), Some(6));
+                assert!(metadata.stored_at().is_some());
+                assert!(listed.insert(receipt.clone()));
+            }
+            pages += 1;
+            cursor = result.next_cursor().cloned();
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(listed, receipts);
+
+        let result = alice.list_shares(None, None).unwrap();
+        assert_eq!(result.receipt_to_metadata().len(), 5);
+        assert!(result.next_cursor().is_none());
+
+        assert!(matches!(
+            remote_error(alice.list_shares(Some(0), None)),
+            DepoError::InvalidRequest(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_xid_document() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice_keys = PrivateKeyBase::new_using(&mut rng);
+        let alice = fixture.client(&alice_keys);
+        alice.store_share(b"data").unwrap();
+
+        let other_xid_document: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        assert!(alice.update_xid_document(other_xid_document.clone()).is_err());
+        alice
+            .update_xid_document(alice.xid_document().clone())
+            .unwrap();
+        assert_eq!(
+            fixture.depo.borrow().xid_document(&alice.xid_document().xid()),
+            Some(alice.xid_document())
+        );
+        assert_eq!(&alice.get_xid_document().unwrap(), alice.xid_document());
+
+        // An update based on a document the depo no longer holds is refused.
+        let stale = UpdateXIDDocument::new(alice.xid_document().clone())
+            .with_previous_xid_document(&other_xid_document);
+        assert_eq!(
+            remote_error(alice.send(stale)),
+            DepoError::XIDDocumentChanged
+        );
+
+        // Rotate to a new key, dropping the inception key.
+        let new_keys = PrivateKeyBase::new_using(&mut rng);
+        let mut rotated = alice.xid_document().clone();
+        rotated
+            .add_key(Key::new_allow_all(new_keys.public_keys()))
+            .unwrap();
+        rotated.remove_inception_key().unwrap();
+        alice.rotate_xid_document(rotated.clone()).unwrap();
+
+        // The dropped inception key no longer reaches the account, nor can it
+        // open a new one under the same XID.
+        assert_eq!(
+            remote_error(alice.get_all_shares()),
+            DepoError::UnknownAccount
+        );
+        assert_eq!(
+            remote_error(alice.store_share(b"data")),
+            DepoError::UnknownAccount
+        );
+
+        // The new key controls the account and can rotate it again.
+        let alice_new = fixture
+            .client_with_document(new_keys.private_keys(), rotated.clone());
+        assert_eq!(alice_new.get_xid_document().unwrap(), rotated);
+        let mut next = rotated.clone();
+        next.add_key(Key::new_allow_all(
+            PrivateKeyBase::new_using(&mut rng).public_keys(),
+        ))
+        .unwrap();
+        alice_new.rotate_xid_document(next.clone()).unwrap();
+        assert_eq!(
+            fixture.depo.borrow().xid_document(&rotated.xid()),
+            Some(&next)
+        );
+        let result = alice_new.get_all_shares().unwrap();
+        assert_eq!(result.receipt_to_data().len(), 1);
+    }
+
+    #[test]
+    fn test_permissions() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        let receipt = alice.store_share(b"data").unwrap();
+
+        // Alice adds a key that may only read the account.
+        let reader_keys = PrivateKeyBase::new_using(&mut rng);
+        let mut reader_key = Key::new(reader_keys.public_keys());
+        reader_key.add_permission(Privilege::Access);
+        let mut xid_document = alice.xid_document().clone();
+        xid_document.add_key(reader_key.clone()).unwrap();
+        alice.update_xid_document(xid_document).unwrap();
+
+        let mut reader_document =
+            XIDDocument::from_xid(alice.xid_document().xid());
+        reader_document.add_key(reader_key).unwrap();
+        let reader = fixture
+            .client_with_document(reader_keys.private_keys(), reader_document);
+        let result = reader.get_all_shares().unwrap();
+        assert_eq!(result.data_for_receipt(&receipt).unwrap().data(), b"data");
+        assert_eq!(reader.get_recovery().unwrap(), None);
+
+        assert_eq!(
+            remote_error(reader.delete_shares(vec![receipt.clone()])),
+            DepoError::PermissionDenied
+        );
+        let xid_document = alice.xid_document().clone();
+        assert_eq!(
+            remote_error(reader.update_xid_document(xid_document)),
+            DepoError::PermissionDenied
+        );
+        assert_eq!(
+            remote_error(reader.store_share(b"more")),
+            DepoError::PermissionDenied
+        );
+        assert_eq!(
+            remote_error(reader.delete_account()),
+            DepoError::PermissionDenied
+        );
+        assert_eq!(fixture.depo.borrow().share_count(), 1);
+    }
+
+    #[test]
+    fn test_recovery() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice_keys = PrivateKeyBase::new_using(&mut rng);
+        let alice = fixture.client(&alice_keys);
+        let bob = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        let alice_new_keys = PrivateKeyBase::new_using(&mut rng);
+        let alice_new = fixture.client(&alice_new_keys);
+
+        let receipt = alice.store_share(b"data").unwrap();
+        bob.store_share(b"data").unwrap();
+        assert_eq!(alice.get_recovery().unwrap(), None);
+
+        let recovery = RecoveryMethod::email("alice@example.com").unwrap();
+        alice.update_recovery(Some(recovery.clone())).unwrap();
+        assert_eq!(alice.get_recovery().unwrap(), Some(recovery.clone()));
+
+        // Recovery methods identify a single account.
+        assert_eq!(
+            remote_error(bob.update_recovery(Some(recovery.clone()))),
+            DepoError::RecoveryMethodInUse
+        );
+
+        // Recovery can't be finished without a continuation from the depo.
+        let forged = Envelope::new("continuation");
+        assert_eq!(
+            remote_error(alice_new.finish_recovery(forged)),
+            DepoError::InvalidContinuation
+        );
+
+        // Nor with one made by someone who knows only the depo's public keys.
+        let depo_public_keys = fixture.depo.borrow().keys.public_keys();
+        let forged = RecoveryContinuation::new(
+            alice.xid_document().xid(),
+            alice_new_keys.public_keys(),
+            Date::from_timestamp(Date::now().timestamp() + 60.0),
+        )
+        .seal(&alice_new_keys.private_keys(), &depo_public_keys);
+        assert_eq!(
+            remote_error(alice_new.finish_recovery(forged)),
+            DepoError::InvalidContinuation
+        );
+
+        let nobody = RecoveryMethod::email("nobody@example.com").unwrap();
+        assert_eq!(
+            remote_error(alice_new.start_recovery(nobody)),
+            DepoError::UnknownRecoveryMethod
+        );
+
+        // The continuation is delivered out of band, not in the response.
+        assert_eq!(alice_new.start_recovery(recovery.clone()).unwrap(), None);
+        let continuation = fixture
+            .depo
+            .borrow_mut()
+            .take_delivered_continuation(&recovery)
+            .unwrap();
+
+        // Only the keys that started recovery can finish it, and only once.
+        assert_eq!(
+            remote_error(bob.finish_recovery(continuation.clone())),
+            DepoError::InvalidContinuation
+        );
+        alice_new.finish_recovery(continuation.clone()).unwrap();
+        assert_eq!(
+            remote_error(alice_new.finish_recovery(continuation)),
+            DepoError::ContinuationUsed
+        );
+
+        // The new keys control the account, and the old ones no longer do.
+        let result = alice_new.get_all_shares().unwrap();
+        assert_eq!(result.data_for_receipt(&receipt).unwrap().data(), b"data");
+        assert_eq!(alice_new.get_recovery().unwrap(), Some(recovery));
+        assert!(alice.get_all_shares().is_err());
+        assert_eq!(
+            remote_error(alice.store_share(b"data")),
+            DepoError::AccountRecovered
+        );
+
+        assert!(alice.update_recovery(None).is_err());
+
+        // Nor can the old keys reach the account by claiming the new XID.
+        let mut forged = XIDDocument::from_xid(alice_new.xid_document().xid());
+        forged
+            .add_key(Key::new_allow_all(alice_keys.public_keys()))
+            .unwrap();
+        let forger =
+            fixture.client_with_document(alice_keys.private_keys(), forged);
+        assert_eq!(
+            remote_error(forger.get_all_shares()),
+            DepoError::UnknownAccount
+        );
+
+        alice_new.update_recovery(None).unwrap();
+        assert_eq!(alice_new.get_recovery().unwrap(), None);
+
+        // The account's XID document is now the new keys', and they can
+        // update it.
+        let xid_document = alice_new.get_xid_document().unwrap();
+        assert_eq!(&xid_document, alice_new.xid_document());
+        assert_eq!(
+            fixture.depo.borrow().xid_document(&alice.xid_document().xid()),
+            Some(alice_new.xid_document())
+        );
+        alice_new
+            .update_xid_document(alice_new.xid_document().clone())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_recovery_by_account_holder() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let fixture = Fixture::new(PrivateKeyBase::new_using(&mut rng));
+        let alice = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+        let bob = fixture.client(&PrivateKeyBase::new_using(&mut rng));
+
+        alice.store_share(b"alice").unwrap();
+        let receipt = bob.store_share(b"bob").unwrap();
+        let recovery = RecoveryMethod::email("alice@example.com").unwrap();
+        alice.update_recovery(Some(recovery.clone())).unwrap();
+
+        // Keys that already hold an account can't take over another, which
+        // would leave their own account unreachable.
+        bob.start_recovery(recovery.clone()).unwrap();
+        let continuation = fixture
+            .depo
+            .borrow_mut()
+            .take_delivered_continuation(&recovery)
+            .unwrap();
+        assert!(matches!(
+            remote_error(bob.finish_recovery(continuation)),
+            DepoError::InvalidRequest(_)
+        ));
+        let result = bob.get_all_shares().unwrap();
+        assert_eq!(result.data_for_receipt(&receipt).unwrap().data(), b"bob");
+        assert_eq!(alice.get_recovery().unwrap(), Some(recovery));
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+
+use bc_components::SigningPublicKey;
+use bc_envelope::prelude::*;
+use bc_xid::{HasPermissions, Privilege, XIDDocument};
+
+use crate::{
+    DELETE_ACCOUNT_FUNCTION, DELETE_SHARES_FUNCTION, DepoError, Error,
+    FINISH_RECOVERY_FUNCTION, GET_RECOVERY_FUNCTION, GET_SHARES_FUNCTION,
+    GET_XID_DOCUMENT_FUNCTION, LIST_SHARES_FUNCTION, Result,
+    START_RECOVERY_FUNCTION, STORE_SHARE_FUNCTION, STORE_SHARES_FUNCTION,
+    UPDATE_RECOVERY_FUNCTION, UPDATE_XID_DOCUMENT_FUNCTION, util::bare_name,
+};
+
+/// The privilege a key of the account's XID document must be allowed to call
+/// `function`, or `None` if any key may call it.
+///
+/// | Function | Privilege |
+/// |----------|-----------|
+/// | `getShares`, `listShares`, `getRecovery`, `getXIDDocument` | `Access` |
+/// | `storeShare`, `storeShares` | `Update` |
+/// | `deleteShares` | `Revoke` |
+/// | `updateXIDDocument`, `updateRecovery` | `Transfer` |
+/// | `deleteAccount` | `Burn` |
+/// | `startRecovery`, `finishRecovery` | none |
+///
+/// Recovery needs no privilege, as it is called with keys the account does
+/// not yet hold.
+pub fn required_privilege(function: &Function) -> Result<Option<Privilege>> {
+    let privilege = if function == &GET_SHARES_FUNCTION
+        || function == &LIST_SHARES_FUNCTION
+        || function == &GET_RECOVERY_FUNCTION
+        || function == &GET_XID_DOCUMENT_FUNCTION
+    {
+        Some(Privilege::Access)
+    } else if function == &STORE_SHARE_FUNCTION
+        || function == &STORE_SHARES_FUNCTION
+    {
+        Some(Privilege::Update)
+    } else if function == &DELETE_SHARES_FUNCTION {
+        Some(Privilege::Revoke)
+    } else if function == &UPDATE_XID_DOCUMENT_FUNCTION
+        || function == &UPDATE_RECOVERY_FUNCTION
+    {
+        Some(Privilege::Transfer)
+    } else if function == &DELETE_ACCOUNT_FUNCTION {
+        Some(Privilege::Burn)
+    } else if function == &START_RECOVERY_FUNCTION
+        || function == &FINISH_RECOVERY_FUNCTION
+    {
+        None
+    } else {
+        return Err(Error::UnknownFunction {
+            function: bare_name(function.name()),
+        });
+    };
+    Ok(privilege)
+}
+
+/// Checks that the key of `account` matching `signer`, the verified key that
+/// signed a sealed request (see `Sender::signing_key`), may call `function`.
+///
+/// A key may call a function if it is allowed the required privilege or
+/// `All`, and denied neither. Otherwise, or if `signer` is not a key of
+/// `account`, the call is refused with `DepoError::PermissionDenied`.
+pub fn check_permission(
+    account: &XIDDocument,
+    signer: &SigningPublicKey,
+    function: &Function,
+) -> Result<()> {
+    let Some(privilege) = required_privilege(function)? else {
+        return Ok(());
+    };
+    let permitted = account
+        .keys()
+        .iter()
+        .find(|key| key.public_keys().signing_public_key() == signer)
+        .is_some_and(|key| {
+            let permissions = key.permissions();
+            let grants = |privileges: &HashSet<Privilege>| {
+                privileges.contains(&Privilege::All)
+                    || privileges.contains(&privilege)
+            };
+            grants(permissions.allow()) && !grants(permissions.deny())
+        });
+    if !permitted {
+        return Err(Error::Remote(DepoError::PermissionDenied));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{PrivateKeyBase, PublicKeysProvider};
+    use bc_rand::make_fake_random_number_generator;
+    use bc_xid::Key;
+
+    use super::*;
+
+    fn is_denied(result: Result<()>) -> bool {
+        matches!(result, Err(Error::Remote(DepoError::PermissionDenied)))
+    }
+
+    #[test]
+    fn test_required_privilege() {
+        assert_eq!(
+            required_privilege(&GET_SHARES_FUNCTION).unwrap(),
+            Some(Privilege::Access)
+        );
+        assert_eq!(
+            required_privilege(&STORE_SHARES_FUNCTION).unwrap(),
+            Some(Privilege::Update)
+        );
+        assert_eq!(
+            required_privilege(&DELETE_ACCOUNT_FUNCTION).unwrap(),
+            Some(Privilege::Burn)
+        );
+        assert_eq!(required_privilege(&START_RECOVERY_FUNCTION).unwrap(), None);
+        assert!(matches!(
+            required_privilege(&Function::new_named("launchMissiles")),
+            Err(Error::UnknownFunction { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_permission() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let owner_keys = PrivateKeyBase::new_using(&mut rng).public_keys();
+        let reader_keys = PrivateKeyBase::new_using(&mut rng).public_keys();
+        let owner = owner_keys.signing_public_key();
+        let reader = reader_keys.signing_public_key();
+        let stranger_keys = PrivateKeyBase::new_using(&mut rng).public_keys();
+        let stranger = stranger_keys.signing_public_key();
+
+        // The inception key is allowed everything; the reader key only
+        // `Access`.
+        let mut account: XIDDocument = owner_keys.clone().into();
+        let mut reader_key = Key::new(reader_keys.clone());
+        reader_key.add_allow(Privilege::Access);
+        account.add_key(reader_key).unwrap();
+
+        for function in [
+            &GET_SHARES_FUNCTION,
+            &STORE_SHARE_FUNCTION,
+            &DELETE_SHARES_FUNCTION,
+            &UPDATE_XID_DOCUMENT_FUNCTION,
+            &DELETE_ACCOUNT_FUNCTION,
+        ] {
+            check_permission(&account, owner, function).unwrap();
+        }
+
+        check_permission(&account, reader, &LIST_SHARES_FUNCTION).unwrap();
+        check_permission(&account, reader, &GET_XID_DOCUMENT_FUNCTION)
+            .unwrap();
+        assert!(is_denied(check_permission(
+            &account,
+            reader,
+            &STORE_SHARE_FUNCTION
+        )));
+        assert!(is_denied(check_permission(
+            &account,
+            reader,
+            &DELETE_ACCOUNT_FUNCTION
+        )));
+
+        assert!(is_denied(check_permission(
+            &account,
+            stranger,
+            &GET_SHARES_FUNCTION
+        )));
+        check_permission(&account, stranger, &FINISH_RECOVERY_FUNCTION)
+            .unwrap();
+    }
+}
@@ -1,34 +1,118 @@
-use bc_components::XID;
+use bc_components::{Salt, XID};
 use bc_envelope::prelude::*;
 
 use crate::{Error, Result};
 
+/// The format of a [`Receipt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReceiptVersion {
+    /// `Digest(xid || data)`. Anyone who knows the user's XID and a
+    /// candidate share can confirm the user stored it.
+    V1,
+    /// `Digest(xid || Digest(salt) || data)`, where the salt is chosen at
+    /// random for each share and held by the depo alongside it. The receipt
+    /// reveals nothing about the share without the salt. The salt is
+    /// digested first so it always takes 32 bytes, and no bytes can be moved
+    /// between a salt of varying length and the data.
+    V2,
+}
+
+/// Identifies a share stored by an account.
+///
+/// Depos may issue both versions during a migration; each is encoded with
+/// its own type so they can be told apart:
+///
+/// ```text
+/// Bytes(32) [
+///     'isA': "SaltedReceipt"
+/// ]
+/// ```
 #[derive(Clone, PartialEq, Eq, Hash)]
-pub struct Receipt(Digest);
+pub struct Receipt {
+    digest: Digest,
+    version: ReceiptVersion,
+}
 
 pub const RECEIPT_TYPE: &str = "Receipt";
+pub const SALTED_RECEIPT_TYPE: &str = "SaltedReceipt";
 
 impl Receipt {
     pub fn new(user_id: XID, data: impl AsRef<[u8]>) -> Self {
-        Self(Digest::from_image_parts(&[user_id.data(), data.as_ref()]))
+        Self {
+            digest: Digest::from_image_parts(&[user_id.data(), data.as_ref()]),
+            version: ReceiptVersion::V1,
+        }
+    }
+
+    pub fn new_salted(
+        user_id: XID,
+        salt: &Salt,
+        data: impl AsRef<[u8]>,
+    ) -> Self {
+        Self {
+            digest: Digest::from_image_parts(&[
+                user_id.data(),
+                Digest::from_image(salt.as_bytes()).data(),
+                data.as_ref(),
+            ]),
+            version: ReceiptVersion::V2,
+        }
+    }
+
+    pub fn version(&self) -> ReceiptVersion { self.version }
+
+    pub fn is_salted(&self) -> bool { self.version == ReceiptVersion::V2 }
+
+    /// Returns `true` if this is the receipt for `data` stored by the
+    /// account `user_id`.
+    ///
+    /// Always `false` for a salted receipt; use `verify_with_salt` instead.
+    pub fn verify(&self, user_id: &XID, data: impl AsRef<[u8]>) -> bool {
+        self.verify_with_salt(user_id, None, data)
+    }
+
+    /// Returns `true` if this is the receipt for `data` stored by the
+    /// account `user_id`, using `salt` if the receipt is salted.
+    pub fn verify_with_salt(
+        &self,
+        user_id: &XID,
+        salt: Option<&Salt>,
+        data: impl AsRef<[u8]>,
+    ) -> bool {
+        let expected = match (self.version, salt) {
+            (ReceiptVersion::V1, _) => Self::new(*user_id, data),
+            (ReceiptVersion::V2, Some(salt)) => {
+                Self::new_salted(*user_id, salt, data)
+            }
+            (ReceiptVersion::V2, None) => return false,
+        };
+        self == &expected
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self.version {
+            ReceiptVersion::V1 => RECEIPT_TYPE,
+            ReceiptVersion::V2 => SALTED_RECEIPT_TYPE,
+        }
     }
 }
 
 impl std::ops::Deref for Receipt {
     type Target = Digest;
 
-    fn deref(&self) -> &Self::Target { &self.0 }
+    fn deref(&self) -> &Self::Target { &self.digest }
 }
 
 impl std::fmt::Debug for Receipt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Receipt({})", hex::encode(self.0))
+        write!(f, "{}({})", self.type_name(), hex::encode(self.digest))
     }
 }
 
 impl From<Receipt> for Envelope {
     fn from(receipt: Receipt) -> Self {
-        Envelope::new(CBOR::to_byte_string(receipt.0)).add_type(RECEIPT_TYPE)
+        Envelope::new(CBOR::to_byte_string(receipt.digest))
+            .add_type(receipt.type_name())
     }
 }
 
@@ -36,12 +120,19 @@ impl TryFrom<Envelope> for Receipt {
     type Error = Error;
 
     fn try_from(envelope: Envelope) -> Result<Self> {
-        envelope
-            .check_type(RECEIPT_TYPE)
-            .map_err(|e| Error::TypeMismatch {
-                expected: RECEIPT_TYPE.to_string(),
-                found: format!("envelope without type or wrong type: {}", e),
-            })?;
+        let version = if envelope.check_type(RECEIPT_TYPE).is_ok() {
+            ReceiptVersion::V1
+        } else if envelope.check_type(SALTED_RECEIPT_TYPE).is_ok() {
+            ReceiptVersion::V2
+        } else {
+            return Err(Error::TypeMismatch {
+                expected: format!(
+                    "{} or {}",
+                    RECEIPT_TYPE, SALTED_RECEIPT_TYPE
+                ),
+                found: "envelope without type or wrong type".to_string(),
+            });
+        };
         let bytes: ByteString =
             envelope
                 .extract_subject()
@@ -56,7 +147,7 @@ impl TryFrom<Envelope> for Receipt {
                 message: format!("failed to create digest from bytes: {}", e),
             }
         })?;
-        Ok(Self(digest))
+        Ok(Self { digest, version })
     }
 }
 
@@ -97,5 +188,61 @@ mod tests {
 
         let receipt_2 = Receipt::try_from(envelope).unwrap();
         assert_eq!(receipt, receipt_2);
+
+        assert!(receipt.verify(&user_id, b"data"));
+        assert!(!receipt.verify(&user_id, b"date"));
+    }
+
+    #[test]
+    fn test_salted_receipt() {
+        bc_envelope::register_tags();
+
+        let user_id = XID::from_data_ref(hex!(
+            "3eadf5bf7a4da69f824be029d2d0ece06fcb3aca7dd85d402b661f7b48f18294"
+        ))
+        .unwrap();
+        let salt = Salt::from_data(hex!("0102030405060708"));
+        let receipt = Receipt::new_salted(user_id, &salt, b"data");
+        assert!(receipt.is_salted());
+        assert_ne!(receipt, Receipt::new(user_id, b"data"));
+        assert_ne!(
+            receipt,
+            Receipt::new_salted(
+                user_id,
+                &Salt::from_data(hex!("0807060504030201")),
+                b"data"
+            )
+        );
+
+        let envelope = receipt.clone().to_envelope();
+        #[rustfmt::skip]
+        assert_eq!(envelope.format(), indoc!{r#"
+            Bytes(32) [
+                'isA': "SaltedReceipt"
+            ]
+        "#}.trim());
+        let receipt_2 = Receipt::try_from(envelope).unwrap();
+        assert_eq!(receipt, receipt_2);
+        assert_eq!(receipt_2.version(), ReceiptVersion::V2);
+
+        assert!(receipt.verify_with_salt(&user_id, Some(&salt), b"data"));
+        assert!(!receipt.verify_with_salt(&user_id, Some(&salt), b"date"));
+        assert!(!receipt.verify(&user_id, b"data"));
+
+        // Moving bytes from the data into the salt yields a different
+        // receipt, so a depo cannot alter the data it returns this way.
+        let shifted_salt = Salt::from_data(hex!("0102030405060708 64"));
+        assert!(!receipt.verify_with_salt(
+            &user_id,
+            Some(&shifted_salt),
+            b"ata"
+        ));
+
+        let untyped =
+            Envelope::new(ByteString::from(receipt.data().to_vec()));
+        assert!(matches!(
+            Receipt::try_from(untyped),
+            Err(Error::TypeMismatch { .. })
+        ));
     }
 }
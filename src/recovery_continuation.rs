@@ -0,0 +1,195 @@
+use bc_components::{
+    ARID, Decrypter, Encrypter, PublicKeys, Signer, Verifier, XID, XIDProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{Error, Result};
+
+pub const RECOVERY_CONTINUATION_TYPE: &str = "RecoveryContinuation";
+
+pub const XID_PREDICATE: &str = "xid";
+pub const NEW_PUBLIC_KEYS_PREDICATE: &str = "newPublicKeys";
+pub const EXPIRY_PREDICATE: &str = "expiry";
+
+/// The state of an account recovery, carried between `startRecovery` and
+/// `finishRecovery` so the depo needn't remember it.
+///
+/// The depo signs the continuation and seals it to itself, then delivers it
+/// to the owner of the recovery method out of band. Sealing alone would not
+/// do, as anyone can encrypt to the depo's public keys, so the signature is
+/// what shows the depo issued it. Presenting it in a `finishRecovery`
+/// request signed by the new keys, before it expires, transfers control of
+/// the account to those keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryContinuation {
+    xid: XID,
+    new_public_keys: PublicKeys,
+    expiry: Date,
+    nonce: ARID,
+}
+
+impl RecoveryContinuation {
+    pub fn new(xid: XID, new_public_keys: PublicKeys, expiry: Date) -> Self {
+        Self { xid, new_public_keys, expiry, nonce: ARID::new() }
+    }
+
+    /// The account being recovered.
+    pub fn xid(&self) -> XID { self.xid }
+
+    /// The keys that will control the account once recovery is finished.
+    pub fn new_public_keys(&self) -> &PublicKeys { &self.new_public_keys }
+
+    pub fn expiry(&self) -> &Date { &self.expiry }
+
+    /// A unique value that lets the depo accept the continuation only once.
+    pub fn nonce(&self) -> ARID { self.nonce }
+
+    pub fn is_expired(&self, now: &Date) -> bool { now > &self.expiry }
+
+    /// Returns `true` if `xid_document` is controlled by the new keys.
+    pub fn is_for(&self, xid_document: &XIDDocument) -> bool {
+        XIDDocument::from(self.new_public_keys.clone()).xid()
+            == xid_document.xid()
+    }
+
+    /// Signs the continuation with `signer` and encrypts it so only
+    /// `recipient`, usually the depo itself, can read it.
+    pub fn seal(
+        &self,
+        signer: &dyn Signer,
+        recipient: &dyn Encrypter,
+    ) -> Envelope {
+        self.to_envelope()
+            .sign(signer)
+            .encrypt_to_recipient(recipient)
+    }
+
+    /// Decrypts a continuation previously sealed with `seal`, and checks it
+    /// was signed by `verifier`.
+    pub fn unseal(
+        envelope: &Envelope,
+        verifier: &dyn Verifier,
+        recipient: &dyn Decrypter,
+    ) -> Result<Self> {
+        let envelope = envelope.decrypt_to_recipient(recipient).map_err(|e| {
+            Error::InvalidEnvelope {
+                message: format!("failed to decrypt continuation: {}", e),
+            }
+        })?;
+        let envelope =
+            envelope.verify(verifier).map_err(|e| Error::InvalidEnvelope {
+                message: format!("failed to verify continuation: {}", e),
+            })?;
+        Self::try_from(envelope)
+    }
+}
+
+impl From<RecoveryContinuation> for Envelope {
+    fn from(value: RecoveryContinuation) -> Self {
+        Envelope::new(value.nonce)
+            .add_type(RECOVERY_CONTINUATION_TYPE)
+            .add_assertion(XID_PREDICATE, value.xid)
+            .add_assertion(NEW_PUBLIC_KEYS_PREDICATE, value.new_public_keys)
+            .add_assertion(EXPIRY_PREDICATE, value.expiry)
+    }
+}
+
+impl TryFrom<Envelope> for RecoveryContinuation {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        envelope.check_type(RECOVERY_CONTINUATION_TYPE).map_err(|e| {
+            Error::TypeMismatch {
+                expected: RECOVERY_CONTINUATION_TYPE.to_string(),
+                found: format!(
+                    "envelope without type or wrong type: {}",
+                    e
+                ),
+            }
+        })?;
+        let invalid = |e: bc_envelope::Error| Error::InvalidEnvelope {
+            message: format!("invalid recovery continuation: {}", e),
+        };
+        let nonce = envelope.extract_subject().map_err(invalid)?;
+        let xid = envelope
+            .extract_object_for_predicate(XID_PREDICATE)
+            .map_err(invalid)?;
+        let new_public_keys = envelope
+            .extract_object_for_predicate(NEW_PUBLIC_KEYS_PREDICATE)
+            .map_err(invalid)?;
+        let expiry = envelope
+            .extract_object_for_predicate(EXPIRY_PREDICATE)
+            .map_err(invalid)?;
+        Ok(Self { xid, new_public_keys, expiry, nonce })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{
+        PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider,
+    };
+    use bc_rand::make_fake_random_number_generator;
+
+    use super::*;
+
+    #[test]
+    fn test_continuation() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let old_xid_document: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        let new_keys = PrivateKeyBase::new_using(&mut rng).public_keys();
+        let new_xid_document: XIDDocument = new_keys.clone().into();
+
+        let expiry = Date::from_timestamp(1_000_000.0);
+        let continuation = RecoveryContinuation::new(
+            old_xid_document.xid(),
+            new_keys,
+            expiry,
+        );
+        assert!(continuation.is_for(&new_xid_document));
+        assert!(!continuation.is_for(&old_xid_document));
+        assert!(!continuation.is_expired(&Date::from_timestamp(999_999.0)));
+        assert!(continuation.is_expired(&Date::from_timestamp(1_000_001.0)));
+
+        let envelope = continuation.to_envelope();
+        let decoded = RecoveryContinuation::try_from(envelope).unwrap();
+        assert_eq!(continuation, decoded);
+
+        let unseal = |sealed: &Envelope| {
+            RecoveryContinuation::unseal(
+                sealed,
+                &depo_keys.public_keys(),
+                &depo_keys.private_keys(),
+            )
+        };
+        let sealed = continuation
+            .seal(&depo_keys.private_keys(), &depo_keys.public_keys());
+        assert!(RecoveryContinuation::try_from(sealed.clone()).is_err());
+        assert_eq!(unseal(&sealed).unwrap(), continuation);
+
+        let other_keys = PrivateKeyBase::new_using(&mut rng);
+        assert!(
+            RecoveryContinuation::unseal(
+                &sealed,
+                &depo_keys.public_keys(),
+                &other_keys.private_keys()
+            )
+            .is_err()
+        );
+
+        // Anyone can encrypt to the depo, but only the depo can sign, so a
+        // continuation it did not create is refused, signed or not.
+        let forged = continuation.to_envelope().encrypt_to_recipient(
+            &depo_keys.public_keys(),
+        );
+        assert!(unseal(&forged).is_err());
+        let forged = continuation
+            .seal(&other_keys.private_keys(), &depo_keys.public_keys());
+        assert!(unseal(&forged).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+use bc_components::{PrivateKeys, PublicKeys, SSKRSpec, XID};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    DepoEndpoint, Error, GetShares, PlannedShare, Receipt, RecoveryPlan, Result,
+};
+
+pub const RECOVERY_MANIFEST_TYPE: &str = "RecoveryManifest";
+
+pub const CREATED_PREDICATE: &str = "created";
+pub const GROUP_THRESHOLD_PREDICATE: &str = "groupThreshold";
+pub const GROUP_PREDICATE: &str = "group";
+pub const MEMBER_THRESHOLD_PREDICATE: &str = "memberThreshold";
+pub const MEMBER_COUNT_PREDICATE: &str = "memberCount";
+pub const DEPO_PREDICATE: &str = "depo";
+pub const INDEX_PREDICATE: &str = "index";
+pub const XID_DOCUMENT_PREDICATE: &str = "xidDocument";
+pub const SHARE_PREDICATE: &str = "share";
+pub const MEMBER_PREDICATE: &str = "member";
+
+/// The group and threshold layout of an SSKR split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareLayout {
+    group_threshold: usize,
+    groups: Vec<GroupLayout>,
+}
+
+/// The threshold and size of one SSKR group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupLayout {
+    member_threshold: usize,
+    member_count: usize,
+}
+
+impl ShareLayout {
+    pub fn new(group_threshold: usize, groups: Vec<GroupLayout>) -> Self {
+        Self { group_threshold, groups }
+    }
+
+    pub fn group_threshold(&self) -> usize { self.group_threshold }
+
+    pub fn groups(&self) -> &[GroupLayout] { &self.groups }
+}
+
+impl From<&SSKRSpec> for ShareLayout {
+    fn from(spec: &SSKRSpec) -> Self {
+        Self::new(
+            spec.group_threshold(),
+            spec.groups()
+                .iter()
+                .map(|group| {
+                    GroupLayout::new(
+                        group.member_threshold(),
+                        group.member_count(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl GroupLayout {
+    pub fn new(member_threshold: usize, member_count: usize) -> Self {
+        Self { member_threshold, member_count }
+    }
+
+    pub fn member_threshold(&self) -> usize { self.member_threshold }
+
+    pub fn member_count(&self) -> usize { self.member_count }
+}
+
+/// A durable record of where the shares of a split secret live: the user
+/// they belong to, the SSKR layout, and for each depo its identity and the
+/// receipts of the shares it holds.
+///
+/// The manifest is kept as a `SealedRecoveryManifest`, signed by the user
+/// and optionally encrypted. Unsealed, it is encoded as:
+///
+/// ```text
+/// XID(8712dfac) [
+///     'isA': "RecoveryManifest"
+///     "created": 2025-01-01
+///     "depo": "https://depo.example.com" [
+///         "index": 0
+///         "share": Bytes(32) [
+///             "group": 0
+///             "member": 0
+///             'isA': "Receipt"
+///         ]
+///         "xidDocument": XID(...) [...]
+///     ]
+///     "group": 0 [
+///         "memberCount": 3
+///         "memberThreshold": 2
+///     ]
+///     "groupThreshold": 1
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryManifest {
+    user_id: XID,
+    created: Date,
+    layout: ShareLayout,
+    plan: RecoveryPlan,
+}
+
+impl RecoveryManifest {
+    pub fn new(user_id: XID, layout: ShareLayout, plan: RecoveryPlan) -> Self {
+        Self { user_id, created: Date::now(), layout, plan }
+    }
+
+    pub fn with_created(mut self, created: Date) -> Self {
+        self.created = created;
+        self
+    }
+
+    pub fn user_id(&self) -> XID { self.user_id }
+
+    pub fn created(&self) -> &Date { &self.created }
+
+    pub fn layout(&self) -> &ShareLayout { &self.layout }
+
+    pub fn plan(&self) -> &RecoveryPlan { &self.plan }
+
+    /// The `getShares` request for each depo, in the order of the plan's
+    /// endpoints.
+    pub fn get_shares_requests(&self) -> Vec<(&DepoEndpoint, GetShares)> {
+        self.plan
+            .endpoints()
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| {
+                (endpoint, self.plan.get_shares_request(index))
+            })
+            .collect()
+    }
+
+    /// Signs the manifest with the user's `private_keys`, and if
+    /// `recipient` is given, encrypts it to them.
+    pub fn seal(
+        &self,
+        private_keys: &PrivateKeys,
+        recipient: Option<&PublicKeys>,
+    ) -> SealedRecoveryManifest {
+        let envelope = self.to_envelope().sign(private_keys);
+        SealedRecoveryManifest(match recipient {
+            Some(recipient) => envelope.encrypt_to_recipient(recipient),
+            None => envelope,
+        })
+    }
+}
+
+impl From<RecoveryManifest> for Envelope {
+    fn from(value: RecoveryManifest) -> Self {
+        let mut envelope = Envelope::new(value.user_id)
+            .add_type(RECOVERY_MANIFEST_TYPE)
+            .add_assertion(CREATED_PREDICATE, value.created)
+            .add_assertion(
+                GROUP_THRESHOLD_PREDICATE,
+                value.layout.group_threshold,
+            );
+        for (index, group) in value.layout.groups.iter().enumerate() {
+            envelope = envelope.add_assertion(
+                GROUP_PREDICATE,
+                Envelope::new(index)
+                    .add_assertion(
+                        MEMBER_THRESHOLD_PREDICATE,
+                        group.member_threshold,
+                    )
+                    .add_assertion(MEMBER_COUNT_PREDICATE, group.member_count),
+            );
+        }
+        for (index, endpoint) in value.plan.endpoints().iter().enumerate() {
+            let mut depo = Envelope::new(endpoint.location())
+                .add_assertion(INDEX_PREDICATE, index)
+                .add_assertion(
+                    XID_DOCUMENT_PREDICATE,
+                    endpoint.xid_document().clone(),
+                );
+            for share in value.plan.shares() {
+                if share.endpoint() != index {
+                    continue;
+                }
+                depo = depo.add_assertion(
+                    SHARE_PREDICATE,
+                    share
+                        .receipt()
+                        .clone()
+                        .into_envelope()
+                        .add_assertion(GROUP_PREDICATE, share.group_index())
+                        .add_assertion(MEMBER_PREDICATE, share.member_index()),
+                );
+            }
+            envelope = envelope.add_assertion(DEPO_PREDICATE, depo);
+        }
+        envelope
+    }
+}
+
+fn invalid_manifest(message: impl std::fmt::Display) -> Error {
+    Error::InvalidEnvelope {
+        message: format!("invalid recovery manifest: {}", message),
+    }
+}
+
+/// Orders entries that carry their position, checking the positions run
+/// from zero without gaps or repeats.
+fn in_order<T>(mut entries: Vec<(usize, T)>) -> Result<Vec<T>> {
+    entries.sort_by_key(|(index, _)| *index);
+    for (expected, (index, _)) in entries.iter().enumerate() {
+        if *index != expected {
+            return Err(invalid_manifest(format!(
+                "expected index {}, found {}",
+                expected, index
+            )));
+        }
+    }
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+impl TryFrom<Envelope> for RecoveryManifest {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        envelope.check_type(RECOVERY_MANIFEST_TYPE).map_err(|e| {
+            Error::TypeMismatch {
+                expected: RECOVERY_MANIFEST_TYPE.to_string(),
+                found: format!("envelope without type or wrong type: {}", e),
+            }
+        })?;
+        let user_id: XID =
+            envelope.extract_subject().map_err(invalid_manifest)?;
+        let created: Date = envelope
+            .extract_object_for_predicate(CREATED_PREDICATE)
+            .map_err(invalid_manifest)?;
+        let group_threshold: usize = envelope
+            .extract_object_for_predicate(GROUP_THRESHOLD_PREDICATE)
+            .map_err(invalid_manifest)?;
+
+        let groups = envelope
+            .objects_for_predicate(GROUP_PREDICATE)
+            .into_iter()
+            .map(|group| {
+                let index: usize =
+                    group.extract_subject().map_err(invalid_manifest)?;
+                let member_threshold = group
+                    .extract_object_for_predicate(MEMBER_THRESHOLD_PREDICATE)
+                    .map_err(invalid_manifest)?;
+                let member_count = group
+                    .extract_object_for_predicate(MEMBER_COUNT_PREDICATE)
+                    .map_err(invalid_manifest)?;
+                Ok((index, GroupLayout::new(member_threshold, member_count)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let layout = ShareLayout::new(group_threshold, in_order(groups)?);
+
+        let mut shares = Vec::new();
+        let mut endpoints = Vec::new();
+        for depo in envelope.objects_for_predicate(DEPO_PREDICATE) {
+            let location: String =
+                depo.extract_subject().map_err(invalid_manifest)?;
+            let index: usize = depo
+                .extract_object_for_predicate(INDEX_PREDICATE)
+                .map_err(invalid_manifest)?;
+            let xid_document = depo
+                .object_for_predicate(XID_DOCUMENT_PREDICATE)
+                .map_err(invalid_manifest)?;
+            let xid_document = XIDDocument::try_from(xid_document)
+                .map_err(invalid_manifest)?;
+            for share in depo.objects_for_predicate(SHARE_PREDICATE) {
+                let group_index = share
+                    .extract_object_for_predicate(GROUP_PREDICATE)
+                    .map_err(invalid_manifest)?;
+                let member_index = share
+                    .extract_object_for_predicate(MEMBER_PREDICATE)
+                    .map_err(invalid_manifest)?;
+                let receipt = Receipt::try_from(share)?;
+                shares.push(PlannedShare::new(
+                    index,
+                    group_index,
+                    member_index,
+                    receipt,
+                ));
+            }
+            endpoints.push((index, DepoEndpoint::new(location, xid_document)));
+        }
+        let endpoints = in_order(endpoints)?;
+        shares.sort_by_key(|share| {
+            (share.endpoint(), share.group_index(), share.member_index())
+        });
+
+        Ok(Self {
+            user_id,
+            created,
+            layout,
+            plan: RecoveryPlan::new(endpoints, shares),
+        })
+    }
+}
+
+/// A `RecoveryManifest` signed by the user, and possibly encrypted to a
+/// recipient, in the form it is stored or shared.
+#[derive(Debug, Clone)]
+pub struct SealedRecoveryManifest(Envelope);
+
+impl SealedRecoveryManifest {
+    pub fn envelope(&self) -> &Envelope { &self.0 }
+
+    pub fn is_encrypted(&self) -> bool { self.0.subject().is_encrypted() }
+
+    /// Decrypts the manifest with `private_keys` if it is encrypted, then
+    /// checks it was signed by `signer` and decodes it.
+    ///
+    /// `signer` must be the user the manifest names, so a manifest signed by
+    /// anyone else is refused even if its signature is valid.
+    pub fn open(
+        &self,
+        signer: &PublicKeys,
+        private_keys: Option<&PrivateKeys>,
+    ) -> Result<RecoveryManifest> {
+        let envelope = if self.is_encrypted() {
+            let private_keys = private_keys.ok_or_else(|| {
+                invalid_manifest("encrypted, but no private keys were given")
+            })?;
+            self.0.decrypt_to_recipient(private_keys)?
+        } else {
+            self.0.clone()
+        };
+        let manifest = RecoveryManifest::try_from(envelope.verify(signer)?)?;
+        if XID::new(signer.signing_public_key()) != manifest.user_id() {
+            return Err(invalid_manifest("not signed by the user it names"));
+        }
+        Ok(manifest)
+    }
+}
+
+impl From<SealedRecoveryManifest> for Envelope {
+    fn from(value: SealedRecoveryManifest) -> Self { value.0 }
+}
+
+impl TryFrom<Envelope> for SealedRecoveryManifest {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> { Ok(Self(envelope)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{
+        PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider, XIDProvider,
+    };
+    use bc_rand::make_fake_random_number_generator;
+
+    use super::*;
+
+    fn manifest(user_keys: &PrivateKeyBase) -> RecoveryManifest {
+        let user_id = XIDDocument::from(user_keys.public_keys()).xid();
+        let mut rng = make_fake_random_number_generator();
+        let endpoints = (0..2)
+            .map(|i| {
+                DepoEndpoint::new(
+                    format!("https://depo{}.example.com", i),
+                    PrivateKeyBase::new_using(&mut rng).public_keys().into(),
+                )
+            })
+            .collect();
+        let mut shares = (0..3)
+            .map(|i| {
+                let receipt = Receipt::new(user_id, format!("share_{}", i));
+                PlannedShare::new(i % 2, 0, i, receipt)
+            })
+            .collect::<Vec<_>>();
+        shares.sort_by_key(|share| {
+            (share.endpoint(), share.group_index(), share.member_index())
+        });
+        RecoveryManifest::new(
+            user_id,
+            ShareLayout::new(1, vec![GroupLayout::new(2, 3)]),
+            RecoveryPlan::new(endpoints, shares),
+        )
+        .with_created(Date::from_timestamp(1_000_000.0))
+    }
+
+    #[test]
+    fn test_manifest() {
+        bc_envelope::register_tags();
+
+        let user_keys = PrivateKeyBase::new();
+        let manifest = manifest(&user_keys);
+        let decoded =
+            RecoveryManifest::try_from(manifest.to_envelope()).unwrap();
+        assert_eq!(manifest, decoded);
+
+        let requests = decoded.get_shares_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0.location(), "https://depo0.example.com");
+        assert_eq!(requests[0].1.receipts().len(), 2);
+        assert_eq!(requests[1].1.receipts().len(), 1);
+    }
+
+    #[test]
+    fn test_sealed_manifest() {
+        bc_envelope::register_tags();
+
+        let user_keys = PrivateKeyBase::new();
+        let manifest = manifest(&user_keys);
+
+        let sealed = manifest.seal(&user_keys.private_keys(), None);
+        assert!(!sealed.is_encrypted());
+        let opened = sealed.open(&user_keys.public_keys(), None).unwrap();
+        assert_eq!(manifest, opened);
+
+        // Only the signer's keys verify the manifest.
+        let other_keys = PrivateKeyBase::new();
+        assert!(sealed.open(&other_keys.public_keys(), None).is_err());
+
+        // A manifest signed by someone other than its user is refused.
+        let forged = manifest.seal(&other_keys.private_keys(), None);
+        assert!(forged.open(&other_keys.public_keys(), None).is_err());
+
+        let sealed = manifest.seal(
+            &user_keys.private_keys(),
+            Some(&user_keys.public_keys()),
+        );
+        assert!(sealed.is_encrypted());
+        assert!(sealed.open(&user_keys.public_keys(), None).is_err());
+        let opened = sealed
+            .open(&user_keys.public_keys(), Some(&user_keys.private_keys()))
+            .unwrap();
+        assert_eq!(manifest, opened);
+
+        let ur_string = sealed.ur_string();
+        assert!(ur_string.starts_with("ur:depo-recovery-manifest/"));
+        let decoded = SealedRecoveryManifest::from_ur_string(ur_string)
+            .unwrap()
+            .open(&user_keys.public_keys(), Some(&user_keys.private_keys()))
+            .unwrap();
+        assert_eq!(manifest, decoded);
+    }
+}
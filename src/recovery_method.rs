@@ -0,0 +1,321 @@
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+pub const RECOVERY_METHOD_TYPE: &str = "RecoveryMethod";
+
+pub const KIND_PREDICATE: &str = "kind";
+
+pub const EMAIL_KIND: &str = "email";
+pub const PHONE_KIND: &str = "phone";
+pub const RECOVERY_CODE_KIND: &str = "code";
+pub const URI_KIND: &str = "uri";
+
+/// How the owner of an account can be reached to prove they may recover it.
+///
+/// Encoded as the method's value with its kind and type as assertions:
+///
+/// ```text
+/// "alice@example.com" [
+///     'isA': "RecoveryMethod"
+///     "kind": "email"
+/// ]
+/// ```
+///
+/// For compatibility with older peers, a plain string without assertions is
+/// also accepted and read as by `parse`.
+///
+/// A `RecoveryMethod` can only be made through its constructors, which
+/// validate the value for its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecoveryMethod(Method);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Method {
+    /// An email address, without a `mailto:` prefix.
+    Email(String),
+    /// A phone number able to receive SMS or calls.
+    Phone(String),
+    /// A secret code the owner keeps offline.
+    RecoveryCode(String),
+    /// Any other contact point, as an absolute URI.
+    Uri(String),
+}
+
+impl RecoveryMethod {
+    pub fn email(address: impl Into<String>) -> Result<Self> {
+        Self::validated(Method::Email(address.into()))
+    }
+
+    pub fn phone(number: impl Into<String>) -> Result<Self> {
+        Self::validated(Method::Phone(number.into()))
+    }
+
+    pub fn recovery_code(code: impl Into<String>) -> Result<Self> {
+        Self::validated(Method::RecoveryCode(code.into()))
+    }
+
+    pub fn uri(uri: impl Into<String>) -> Result<Self> {
+        Self::validated(Method::Uri(uri.into()))
+    }
+
+    /// Reads a recovery method from a plain string, as older peers send it.
+    ///
+    /// Older peers sent email addresses as bare strings, so a bare address
+    /// is still read as an email. Other kinds are taken only from an explicit
+    /// scheme, never guessed from the shape of the value: `mailto:` and
+    /// `tel:` URIs are email addresses and phone numbers, other absolute URIs
+    /// are kept as URIs, and anything else, including a bare number, is a
+    /// recovery code.
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(address) = strip_scheme(s, "mailto") {
+            Self::email(address)
+        } else if let Some(number) = strip_scheme(s, "tel") {
+            Self::phone(number)
+        } else if is_uri(s) {
+            Self::uri(s)
+        } else if is_email(s) {
+            Self::email(s)
+        } else {
+            Self::recovery_code(s)
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match &self.0 {
+            Method::Email(_) => EMAIL_KIND,
+            Method::Phone(_) => PHONE_KIND,
+            Method::RecoveryCode(_) => RECOVERY_CODE_KIND,
+            Method::Uri(_) => URI_KIND,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match &self.0 {
+            Method::Email(value)
+            | Method::Phone(value)
+            | Method::RecoveryCode(value)
+            | Method::Uri(value) => value,
+        }
+    }
+
+    fn validated(method: Method) -> Result<Self> {
+        let valid = match &method {
+            Method::Email(address) => is_email(address),
+            Method::Phone(number) => is_phone(number),
+            Method::RecoveryCode(code) => {
+                !code.is_empty() && !code.chars().any(char::is_control)
+            }
+            Method::Uri(uri) => is_uri(uri),
+        };
+        let method = Self(method);
+        if valid {
+            Ok(method)
+        } else {
+            Err(Error::InvalidRecoveryMethod {
+                message: format!(
+                    "invalid {}: {:?}",
+                    method.kind(),
+                    method.value()
+                ),
+            })
+        }
+    }
+
+    pub(crate) fn from_kind(kind: &str, value: String) -> Result<Self> {
+        match kind {
+            EMAIL_KIND => Self::email(value),
+            PHONE_KIND => Self::phone(value),
+            RECOVERY_CODE_KIND => Self::recovery_code(value),
+            URI_KIND => Self::uri(value),
+            _ => Err(Error::InvalidRecoveryMethod {
+                message: format!("unknown kind: {:?}", kind),
+            }),
+        }
+    }
+}
+
+fn strip_scheme<'a>(s: &'a str, scheme: &str) -> Option<&'a str> {
+    let (prefix, rest) = s.split_once(':')?;
+    prefix.eq_ignore_ascii_case(scheme).then_some(rest)
+}
+
+fn is_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains('@')
+        && !s.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn is_phone(s: &str) -> bool {
+    let digits = s.strip_prefix('+').unwrap_or(s);
+    let digit_count = digits.chars().filter(char::is_ascii_digit).count();
+    digits
+        .chars()
+        .all(|c| c.is_ascii_digit() || " -().".contains(c))
+        && (7..=15).contains(&digit_count)
+}
+
+fn is_uri(s: &str) -> bool {
+    let Some((scheme, rest)) = s.split_once(':') else {
+        return false;
+    };
+    let mut scheme_chars = scheme.chars();
+    scheme_chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme_chars.all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+        && !rest.is_empty()
+        && !s.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+impl std::str::FromStr for RecoveryMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> { Self::parse(s) }
+}
+
+impl From<RecoveryMethod> for Envelope {
+    fn from(value: RecoveryMethod) -> Self {
+        Envelope::new(value.value())
+            .add_type(RECOVERY_METHOD_TYPE)
+            .add_assertion(KIND_PREDICATE, value.kind())
+    }
+}
+
+impl TryFrom<Envelope> for RecoveryMethod {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let value: String =
+            envelope
+                .extract_subject()
+                .map_err(|e| Error::InvalidEnvelope {
+                    message: format!(
+                        "failed to extract recovery method: {}",
+                        e
+                    ),
+                })?;
+        if envelope.assertions().is_empty() {
+            return Self::parse(&value);
+        }
+        envelope
+            .check_type(RECOVERY_METHOD_TYPE)
+            .map_err(|e| Error::TypeMismatch {
+                expected: RECOVERY_METHOD_TYPE.to_string(),
+                found: format!("envelope without type or wrong type: {}", e),
+            })?;
+        let kind: String = envelope
+            .extract_object_for_predicate(KIND_PREDICATE)
+            .map_err(|e| Error::InvalidEnvelope {
+                message: format!("failed to extract recovery kind: {}", e),
+            })?;
+        Self::from_kind(&kind, value)
+    }
+}
+
+impl std::fmt::Display for RecoveryMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_recovery_method() {
+        bc_envelope::register_tags();
+
+        let method = RecoveryMethod::email("alice@example.com").unwrap();
+        let envelope = method.to_envelope();
+        // println!("{}", envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(envelope.format(), indoc! {r#"
+            "alice@example.com" [
+                'isA': "RecoveryMethod"
+                "kind": "email"
+            ]
+        "#}.trim());
+        let decoded = RecoveryMethod::try_from(envelope).unwrap();
+        assert_eq!(method, decoded);
+
+        for method in [
+            RecoveryMethod::phone("+1 555 010 9999").unwrap(),
+            RecoveryMethod::recovery_code("correct horse").unwrap(),
+            RecoveryMethod::uri("https://example.com/recover").unwrap(),
+        ] {
+            let decoded =
+                RecoveryMethod::try_from(method.to_envelope()).unwrap();
+            assert_eq!(method, decoded);
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(RecoveryMethod::email("alice").is_err());
+        assert!(RecoveryMethod::email("alice@localhost").is_err());
+        assert!(RecoveryMethod::email("alice@@example.com").is_err());
+        assert!(RecoveryMethod::phone("555").is_err());
+        assert!(RecoveryMethod::phone("555-CALL-NOW").is_err());
+        assert!(RecoveryMethod::recovery_code("").is_err());
+        assert!(RecoveryMethod::uri("example.com").is_err());
+
+        // Typed encodings are validated on decode.
+        let envelope = Envelope::new("alice")
+            .add_type(RECOVERY_METHOD_TYPE)
+            .add_assertion(KIND_PREDICATE, EMAIL_KIND);
+        assert!(RecoveryMethod::try_from(envelope).is_err());
+
+        let envelope = Envelope::new("alice")
+            .add_type(RECOVERY_METHOD_TYPE)
+            .add_assertion(KIND_PREDICATE, "pigeon");
+        assert!(RecoveryMethod::try_from(envelope).is_err());
+    }
+
+    #[test]
+    fn test_plain_strings() {
+        bc_envelope::register_tags();
+
+        let parse = |s: &str| RecoveryMethod::try_from(Envelope::new(s));
+        assert_eq!(
+            parse("mailto:alice@example.com").unwrap(),
+            RecoveryMethod::email("alice@example.com").unwrap()
+        );
+        assert_eq!(
+            parse("tel:+15550109999").unwrap(),
+            RecoveryMethod::phone("+15550109999").unwrap()
+        );
+        assert_eq!(
+            parse("https://example.com/recover").unwrap(),
+            RecoveryMethod::uri("https://example.com/recover").unwrap()
+        );
+        assert_eq!(
+            parse("recovery").unwrap(),
+            RecoveryMethod::recovery_code("recovery").unwrap()
+        );
+
+        // Bare addresses are emails, as older peers sent them.
+        assert_eq!(
+            parse("alice@example.com").unwrap(),
+            RecoveryMethod::email("alice@example.com").unwrap()
+        );
+
+        // Otherwise the kind is not guessed from the value, so a numeric
+        // code stays a code.
+        assert_eq!(
+            parse("12345678").unwrap(),
+            RecoveryMethod::recovery_code("12345678").unwrap()
+        );
+        assert!(parse("mailto:alice").is_err());
+        assert!(parse("").is_err());
+    }
+}
@@ -1,6 +1,9 @@
 use bc_envelope::prelude::*;
 
-use crate::{DELETE_ACCOUNT_FUNCTION, Error, Result, util::FlankedFunction};
+use crate::{
+    DELETE_ACCOUNT_FUNCTION, DepoFunction, Error, OkResult, Result,
+    util::{FlankedFunction, check_expression},
+};
 
 //
 // Request
@@ -26,7 +29,15 @@ impl From<DeleteAccount> for Expression {
 impl TryFrom<Expression> for DeleteAccount {
     type Error = Error;
 
-    fn try_from(_: Expression) -> Result<Self> { Ok(Self::new()) }
+    fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(&expression, &DELETE_ACCOUNT_FUNCTION, &[], &[])?;
+        Ok(Self::new())
+    }
+}
+
+impl DepoFunction for DeleteAccount {
+    const FUNCTION: Function = DELETE_ACCOUNT_FUNCTION;
+    type Response = OkResult;
 }
 
 impl std::fmt::Display for DeleteAccount {
@@ -59,4 +70,22 @@ mod tests {
         let decoded = DeleteAccount::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
     }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::DELETE_SHARES_FUNCTION);
+        assert!(matches!(
+            DeleteAccount::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(DELETE_ACCOUNT_FUNCTION)
+            .with_parameter("extra", "value");
+        assert_eq!(
+            DeleteAccount::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+    }
 }
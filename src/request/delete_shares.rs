@@ -3,9 +3,9 @@ use std::collections::HashSet;
 use bc_envelope::prelude::*;
 
 use crate::{
-    DELETE_SHARES_FUNCTION, Error, RECEIPT_PARAM, RECEIPT_PARAM_NAME, Result,
-    receipt::Receipt,
-    util::{Abbrev, FlankedFunction},
+    DELETE_SHARES_FUNCTION, DepoFunction, Error, OkResult, RECEIPT_PARAM,
+    RECEIPT_PARAM_NAME, Result, receipt::Receipt,
+    util::{Abbrev, FlankedFunction, check_expression},
 };
 
 //
@@ -46,6 +46,12 @@ impl TryFrom<Expression> for DeleteShares {
     type Error = Error;
 
     fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &DELETE_SHARES_FUNCTION,
+            &[],
+            &[RECEIPT_PARAM],
+        )?;
         let receipts = expression
             .objects_for_parameter(RECEIPT_PARAM)
             .into_iter()
@@ -60,6 +66,11 @@ impl TryFrom<Expression> for DeleteShares {
     }
 }
 
+impl DepoFunction for DeleteShares {
+    const FUNCTION: Function = DELETE_SHARES_FUNCTION;
+    type Response = OkResult;
+}
+
 impl std::fmt::Display for DeleteShares {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -114,4 +125,24 @@ mod tests {
         let decoded = DeleteShares::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
     }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::GET_SHARES_FUNCTION)
+            .with_parameter(RECEIPT_PARAM, receipt_1());
+        assert!(matches!(
+            DeleteShares::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(DELETE_SHARES_FUNCTION)
+            .with_parameter(RECEIPT_PARAM, receipt_1())
+            .with_parameter(crate::DATA_PARAM, ByteString::from(b"data"));
+        assert_eq!(
+            DeleteShares::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: data"
+        );
+    }
 }
@@ -0,0 +1,47 @@
+use bc_envelope::prelude::*;
+use gstp::prelude::*;
+
+use crate::Error;
+
+/// A request to a depo function, linked to the type of its successful result.
+///
+/// This lets a generic client send any request and decode the matching
+/// result without inspecting the function by hand.
+pub trait DepoFunction:
+    Into<Expression> + TryFrom<Expression, Error = Error>
+{
+    /// The function invoked by this request.
+    const FUNCTION: Function;
+
+    /// The result returned by the depo when the request succeeds.
+    type Response: TryFrom<SealedResponse, Error = Error>
+        + TryFrom<Envelope, Error = Error>
+        + Into<Envelope>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    fn function_of<F: DepoFunction>() -> Function { F::FUNCTION }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(function_of::<DeleteAccount>(), DELETE_ACCOUNT_FUNCTION);
+        assert_eq!(function_of::<DeleteShares>(), DELETE_SHARES_FUNCTION);
+        assert_eq!(function_of::<FinishRecovery>(), FINISH_RECOVERY_FUNCTION);
+        assert_eq!(function_of::<GetRecovery>(), GET_RECOVERY_FUNCTION);
+        assert_eq!(function_of::<GetShares>(), GET_SHARES_FUNCTION);
+        assert_eq!(function_of::<GetXIDDocument>(), GET_XID_DOCUMENT_FUNCTION);
+        assert_eq!(function_of::<ListShares>(), LIST_SHARES_FUNCTION);
+        assert_eq!(function_of::<StartRecovery>(), START_RECOVERY_FUNCTION);
+        assert_eq!(function_of::<StoreShare>(), STORE_SHARE_FUNCTION);
+        assert_eq!(function_of::<StoreShares>(), STORE_SHARES_FUNCTION);
+        assert_eq!(function_of::<UpdateRecovery>(), UPDATE_RECOVERY_FUNCTION);
+        assert_eq!(
+            function_of::<UpdateXIDDocument>(),
+            UPDATE_XID_DOCUMENT_FUNCTION
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use bc_envelope::prelude::*;
+
+use crate::{
+    DELETE_ACCOUNT_FUNCTION, DELETE_SHARES_FUNCTION, DeleteAccount,
+    DeleteShares, Error, FINISH_RECOVERY_FUNCTION, FinishRecovery,
+    GET_RECOVERY_FUNCTION, GET_SHARES_FUNCTION, GET_XID_DOCUMENT_FUNCTION,
+    GetRecovery, GetShares, GetXIDDocument, LIST_SHARES_FUNCTION, ListShares,
+    Result, START_RECOVERY_FUNCTION, STORE_SHARES_FUNCTION,
+    STORE_SHARE_FUNCTION, StartRecovery, StoreShare, StoreShares,
+    UPDATE_RECOVERY_FUNCTION, UPDATE_XID_DOCUMENT_FUNCTION, UpdateRecovery,
+    UpdateXIDDocument, util::bare_name,
+};
+
+/// Any request understood by a depo, decoded from an `Expression` by its
+/// function.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum DepoRequest {
+    DeleteAccount(DeleteAccount),
+    DeleteShares(DeleteShares),
+    FinishRecovery(FinishRecovery),
+    GetRecovery(GetRecovery),
+    GetShares(GetShares),
+    GetXIDDocument(GetXIDDocument),
+    ListShares(ListShares),
+    StartRecovery(StartRecovery),
+    StoreShare(StoreShare),
+    StoreShares(StoreShares),
+    UpdateRecovery(UpdateRecovery),
+    UpdateXIDDocument(UpdateXIDDocument),
+}
+
+impl DepoRequest {
+    pub fn function(&self) -> Function {
+        match self {
+            Self::DeleteAccount(_) => DELETE_ACCOUNT_FUNCTION,
+            Self::DeleteShares(_) => DELETE_SHARES_FUNCTION,
+            Self::FinishRecovery(_) => FINISH_RECOVERY_FUNCTION,
+            Self::GetRecovery(_) => GET_RECOVERY_FUNCTION,
+            Self::GetShares(_) => GET_SHARES_FUNCTION,
+            Self::GetXIDDocument(_) => GET_XID_DOCUMENT_FUNCTION,
+            Self::ListShares(_) => LIST_SHARES_FUNCTION,
+            Self::StartRecovery(_) => START_RECOVERY_FUNCTION,
+            Self::StoreShare(_) => STORE_SHARE_FUNCTION,
+            Self::StoreShares(_) => STORE_SHARES_FUNCTION,
+            Self::UpdateRecovery(_) => UPDATE_RECOVERY_FUNCTION,
+            Self::UpdateXIDDocument(_) => UPDATE_XID_DOCUMENT_FUNCTION,
+        }
+    }
+
+    /// Whether a replay of the request must be refused: carrying it out
+    /// again would store shares the sender did not ask for twice, remove or
+    /// replace account state, or start or finish another recovery.
+    pub fn is_replay_protected(&self) -> bool {
+        matches!(
+            self,
+            Self::DeleteAccount(_)
+                | Self::DeleteShares(_)
+                | Self::FinishRecovery(_)
+                | Self::StartRecovery(_)
+                | Self::StoreShare(_)
+                | Self::StoreShares(_)
+                | Self::UpdateRecovery(_)
+                | Self::UpdateXIDDocument(_)
+        )
+    }
+}
+
+impl From<DepoRequest> for Expression {
+    fn from(value: DepoRequest) -> Self {
+        match value {
+            DepoRequest::DeleteAccount(request) => request.into(),
+            DepoRequest::DeleteShares(request) => request.into(),
+            DepoRequest::FinishRecovery(request) => request.into(),
+            DepoRequest::GetRecovery(request) => request.into(),
+            DepoRequest::GetShares(request) => request.into(),
+            DepoRequest::GetXIDDocument(request) => request.into(),
+            DepoRequest::ListShares(request) => request.into(),
+            DepoRequest::StartRecovery(request) => request.into(),
+            DepoRequest::StoreShare(request) => request.into(),
+            DepoRequest::StoreShares(request) => request.into(),
+            DepoRequest::UpdateRecovery(request) => request.into(),
+            DepoRequest::UpdateXIDDocument(request) => request.into(),
+        }
+    }
+}
+
+impl TryFrom<Expression> for DepoRequest {
+    type Error = Error;
+
+    fn try_from(expression: Expression) -> Result<Self> {
+        let function = expression.function().clone();
+        let request = if function == DELETE_ACCOUNT_FUNCTION {
+            Self::DeleteAccount(expression.try_into()?)
+        } else if function == DELETE_SHARES_FUNCTION {
+            Self::DeleteShares(expression.try_into()?)
+        } else if function == FINISH_RECOVERY_FUNCTION {
+            Self::FinishRecovery(expression.try_into()?)
+        } else if function == GET_RECOVERY_FUNCTION {
+            Self::GetRecovery(expression.try_into()?)
+        } else if function == GET_SHARES_FUNCTION {
+            Self::GetShares(expression.try_into()?)
+        } else if function == GET_XID_DOCUMENT_FUNCTION {
+            Self::GetXIDDocument(expression.try_into()?)
+        } else if function == LIST_SHARES_FUNCTION {
+            Self::ListShares(expression.try_into()?)
+        } else if function == START_RECOVERY_FUNCTION {
+            Self::StartRecovery(expression.try_into()?)
+        } else if function == STORE_SHARE_FUNCTION {
+            Self::StoreShare(expression.try_into()?)
+        } else if function == STORE_SHARES_FUNCTION {
+            Self::StoreShares(expression.try_into()?)
+        } else if function == UPDATE_RECOVERY_FUNCTION {
+            Self::UpdateRecovery(expression.try_into()?)
+        } else if function == UPDATE_XID_DOCUMENT_FUNCTION {
+            Self::UpdateXIDDocument(expression.try_into()?)
+        } else {
+            return Err(Error::UnknownFunction {
+            function: bare_name(function.name()),
+        });
+        };
+        Ok(request)
+    }
+}
+
+impl From<DeleteAccount> for DepoRequest {
+    fn from(value: DeleteAccount) -> Self { Self::DeleteAccount(value) }
+}
+
+impl From<DeleteShares> for DepoRequest {
+    fn from(value: DeleteShares) -> Self { Self::DeleteShares(value) }
+}
+
+impl From<FinishRecovery> for DepoRequest {
+    fn from(value: FinishRecovery) -> Self { Self::FinishRecovery(value) }
+}
+
+impl From<GetRecovery> for DepoRequest {
+    fn from(value: GetRecovery) -> Self { Self::GetRecovery(value) }
+}
+
+impl From<GetShares> for DepoRequest {
+    fn from(value: GetShares) -> Self { Self::GetShares(value) }
+}
+
+impl From<GetXIDDocument> for DepoRequest {
+    fn from(value: GetXIDDocument) -> Self { Self::GetXIDDocument(value) }
+}
+
+impl From<ListShares> for DepoRequest {
+    fn from(value: ListShares) -> Self { Self::ListShares(value) }
+}
+
+impl From<StartRecovery> for DepoRequest {
+    fn from(value: StartRecovery) -> Self { Self::StartRecovery(value) }
+}
+
+impl From<StoreShare> for DepoRequest {
+    fn from(value: StoreShare) -> Self { Self::StoreShare(value) }
+}
+
+impl From<StoreShares> for DepoRequest {
+    fn from(value: StoreShares) -> Self { Self::StoreShares(value) }
+}
+
+impl From<UpdateRecovery> for DepoRequest {
+    fn from(value: UpdateRecovery) -> Self { Self::UpdateRecovery(value) }
+}
+
+impl From<UpdateXIDDocument> for DepoRequest {
+    fn from(value: UpdateXIDDocument) -> Self {
+        Self::UpdateXIDDocument(value)
+    }
+}
+
+impl std::fmt::Display for DepoRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DeleteAccount(request) => request.fmt(f),
+            Self::DeleteShares(request) => request.fmt(f),
+            Self::FinishRecovery(request) => request.fmt(f),
+            Self::GetRecovery(request) => request.fmt(f),
+            Self::GetShares(request) => request.fmt(f),
+            Self::GetXIDDocument(request) => request.fmt(f),
+            Self::ListShares(request) => request.fmt(f),
+            Self::StartRecovery(request) => request.fmt(f),
+            Self::StoreShare(request) => request.fmt(f),
+            Self::StoreShares(request) => request.fmt(f),
+            Self::UpdateRecovery(request) => request.fmt(f),
+            Self::UpdateXIDDocument(request) => request.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{PrivateKeyBase, PublicKeysProvider, XID};
+    use bc_rand::make_fake_random_number_generator;
+    use bc_xid::XIDDocument;
+
+    use super::*;
+    use crate::{Receipt, RecoveryMethod};
+
+    fn user_id() -> XID {
+        XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap()
+    }
+
+    fn round_trip(request: DepoRequest) {
+        let expression: Expression = request.clone().into();
+        let envelope = expression.to_envelope();
+        let decoded_expression = Expression::try_from(envelope).unwrap();
+        let decoded = DepoRequest::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+        assert_eq!(request.function(), decoded.function());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let xid_document: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        let receipt = Receipt::new(user_id(), b"data");
+        let recovery = RecoveryMethod::email("alice@example.com").unwrap();
+
+        round_trip(DeleteAccount::new().into());
+        round_trip(DeleteShares::new(vec![receipt.clone()]).into());
+        round_trip(FinishRecovery::new(Envelope::new("continuation")).into());
+        round_trip(GetRecovery::new().into());
+        round_trip(GetShares::new(vec![receipt]).into());
+        round_trip(GetShares::new_all_shares().into());
+        round_trip(GetXIDDocument::new().into());
+        round_trip(ListShares::new().with_limit(10).into());
+        round_trip(StartRecovery::new(recovery.clone()).into());
+        round_trip(StoreShare::new(b"data").into());
+        round_trip(StoreShares::new_from_data([b"data_1", b"data_2"]).into());
+        round_trip(UpdateRecovery::new(Some(recovery)).into());
+        round_trip(UpdateRecovery::new(None).into());
+        round_trip(UpdateXIDDocument::new(xid_document).into());
+    }
+
+    #[test]
+    fn test_replay_protected() {
+        let recovery = RecoveryMethod::email("alice@example.com").unwrap();
+        let request: DepoRequest = StartRecovery::new(recovery).into();
+        assert!(request.is_replay_protected());
+        let request: DepoRequest =
+            FinishRecovery::new(Envelope::new("continuation")).into();
+        assert!(request.is_replay_protected());
+        let request: DepoRequest = DeleteAccount::new().into();
+        assert!(request.is_replay_protected());
+        let request: DepoRequest = StoreShare::new(b"data").into();
+        assert!(request.is_replay_protected());
+        let request: DepoRequest = GetRecovery::new().into();
+        assert!(!request.is_replay_protected());
+    }
+
+    #[test]
+    fn test_unknown_function() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new("launchMissiles");
+        let error = DepoRequest::try_from(expression).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::UnknownFunction { function } if function == "launchMissiles"
+        ));
+    }
+}
@@ -1,61 +1,135 @@
 use bc_envelope::prelude::*;
 
-use crate::{Error, FINISH_RECOVERY_FUNCTION, Result, util::FlankedFunction};
+use crate::{
+    DepoFunction, Error, FINISH_RECOVERY_FUNCTION, OkResult,
+    RECOVERY_CONTINUATION_PARAM, RECOVERY_CONTINUATION_PARAM_NAME, Result,
+    util::{Abbrev, FlankedFunction, check_expression},
+};
 
 //
 // Request
 //
 
+/// Completes an account recovery by presenting the sealed
+/// `RecoveryContinuation` the depo delivered after `startRecovery`.
 #[derive(Debug, Clone, PartialEq)]
-pub struct FinishRecovery();
+pub struct FinishRecovery(Envelope);
 
 impl FinishRecovery {
-    pub fn new() -> Self { Self() }
-}
+    pub fn new(continuation: Envelope) -> Self { Self(continuation) }
 
-impl Default for FinishRecovery {
-    fn default() -> Self { Self::new() }
+    pub fn continuation(&self) -> &Envelope { &self.0 }
 }
 
 impl From<FinishRecovery> for Expression {
-    fn from(_: FinishRecovery) -> Self {
+    fn from(value: FinishRecovery) -> Self {
         Expression::new(FINISH_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_CONTINUATION_PARAM, value.0)
     }
 }
 
 impl TryFrom<Expression> for FinishRecovery {
     type Error = Error;
 
-    fn try_from(_: Expression) -> Result<Self> { Ok(Self::new()) }
+    fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &FINISH_RECOVERY_FUNCTION,
+            &[RECOVERY_CONTINUATION_PARAM],
+            &[],
+        )?;
+        let continuation = expression
+            .object_for_parameter(RECOVERY_CONTINUATION_PARAM)
+            .map_err(|_e| Error::MissingParameter {
+                parameter: RECOVERY_CONTINUATION_PARAM_NAME.to_string(),
+            })?;
+        Ok(Self::new(continuation))
+    }
+}
+
+impl DepoFunction for FinishRecovery {
+    const FUNCTION: Function = FINISH_RECOVERY_FUNCTION;
+    type Response = OkResult;
 }
 
 impl std::fmt::Display for FinishRecovery {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", "finishRecovery".flanked_function()))
+        f.write_fmt(format_args!(
+            "{} {}",
+            "finishRecovery".flanked_function(),
+            self.continuation().abbrev()
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use bc_components::{
+        PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider, XID,
+    };
+    use bc_rand::make_fake_random_number_generator;
     use indoc::indoc;
 
     use super::*;
+    use crate::RecoveryContinuation;
+
+    fn continuation() -> Envelope {
+        let mut rng = make_fake_random_number_generator();
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let new_keys = PrivateKeyBase::new_using(&mut rng).public_keys();
+        let xid = XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap();
+        RecoveryContinuation::new(xid, new_keys, Date::from_timestamp(0.0))
+            .seal(&depo_keys.private_keys(), &depo_keys.public_keys())
+    }
 
     #[test]
     fn test_request() {
         bc_envelope::register_tags();
 
-        let request = FinishRecovery::new();
+        let request = FinishRecovery::new(continuation());
         let expression: Expression = request.clone().into();
         let request_envelope = expression.to_envelope();
         // println!("{}", request_envelope.format());
         #[rustfmt::skip]
         assert_eq!(request_envelope.format(), indoc! {r#"
-            «"finishRecovery"»
+            «"finishRecovery"» [
+                ❰"recoveryContinuation"❱: ENCRYPTED [
+                    'hasRecipient': SealedMessage
+                ]
+            ]
         "#}.trim());
         let decoded_expression =
             Expression::try_from(request_envelope).unwrap();
         let decoded = FinishRecovery::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
     }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::START_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_CONTINUATION_PARAM, continuation());
+        assert!(matches!(
+            FinishRecovery::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(FINISH_RECOVERY_FUNCTION);
+        assert_eq!(
+            FinishRecovery::try_from(expression).unwrap_err().to_string(),
+            "missing required parameter: recoveryContinuation"
+        );
+
+        let expression = Expression::new(FINISH_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_CONTINUATION_PARAM, continuation())
+            .with_parameter("extra", "value");
+        assert_eq!(
+            FinishRecovery::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+    }
 }
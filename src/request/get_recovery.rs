@@ -2,8 +2,8 @@ use bc_envelope::prelude::*;
 use gstp::prelude::*;
 
 use crate::{
-    Error, GET_RECOVERY_FUNCTION, Result,
-    util::{Abbrev, FlankedFunction},
+    DepoFunction, Error, GET_RECOVERY_FUNCTION, RecoveryMethod, Result,
+    util::{Abbrev, FlankedFunction, check_expression, response_result},
 };
 
 //
@@ -28,7 +28,15 @@ impl From<GetRecovery> for Expression {
 impl TryFrom<Expression> for GetRecovery {
     type Error = Error;
 
-    fn try_from(_: Expression) -> Result<Self> { Ok(Self::new()) }
+    fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(&expression, &GET_RECOVERY_FUNCTION, &[], &[])?;
+        Ok(Self::new())
+    }
+}
+
+impl DepoFunction for GetRecovery {
+    const FUNCTION: Function = GET_RECOVERY_FUNCTION;
+    type Response = GetRecoveryResult;
 }
 
 impl std::fmt::Display for GetRecovery {
@@ -42,17 +50,17 @@ impl std::fmt::Display for GetRecovery {
 //
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct GetRecoveryResult(Option<String>);
+pub struct GetRecoveryResult(Option<RecoveryMethod>);
 
 impl GetRecoveryResult {
-    pub fn new(recovery: Option<String>) -> Self { Self(recovery) }
+    pub fn new(recovery: Option<RecoveryMethod>) -> Self { Self(recovery) }
 
-    pub fn recovery(&self) -> Option<&str> { self.0.as_deref() }
+    pub fn recovery(&self) -> Option<&RecoveryMethod> { self.0.as_ref() }
 }
 
 impl From<GetRecoveryResult> for Envelope {
     fn from(value: GetRecoveryResult) -> Self {
-        value.recovery().map_or_else(Envelope::null, Envelope::new)
+        value.0.map_or_else(Envelope::null, Envelope::from)
     }
 }
 
@@ -63,14 +71,7 @@ impl TryFrom<Envelope> for GetRecoveryResult {
         let recovery = if envelope.is_null() {
             None
         } else {
-            Some(envelope.extract_subject().map_err(|e| {
-                Error::InvalidEnvelope {
-                    message: format!(
-                        "failed to extract recovery subject: {}",
-                        e
-                    ),
-                }
-            })?)
+            Some(RecoveryMethod::try_from(envelope)?)
         };
         Ok(Self::new(recovery))
     }
@@ -80,7 +81,7 @@ impl TryFrom<SealedResponse> for GetRecoveryResult {
     type Error = Error;
 
     fn try_from(response: SealedResponse) -> Result<Self> {
-        response.result()?.clone().try_into()
+        response_result(&response)?.try_into()
     }
 }
 
@@ -121,13 +122,17 @@ mod tests {
     fn test_response() {
         bc_envelope::register_tags();
 
-        let response = GetRecoveryResult::new(Some("Recovery Method".into()));
+        let recovery = RecoveryMethod::email("alice@example.com").unwrap();
+        let response = GetRecoveryResult::new(Some(recovery));
         let response_envelope = response.to_envelope();
         assert_eq!(
             response_envelope.format(),
             (indoc! {
                 r#"
-        "Recovery Method"
+        "alice@example.com" [
+            'isA': "RecoveryMethod"
+            "kind": "email"
+        ]
         "#
             })
             .trim()
@@ -149,4 +154,22 @@ mod tests {
         let decoded = GetRecoveryResult::try_from(response_envelope).unwrap();
         assert_eq!(response, decoded);
     }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::UPDATE_RECOVERY_FUNCTION);
+        assert!(matches!(
+            GetRecovery::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(GET_RECOVERY_FUNCTION)
+            .with_parameter("extra", "value");
+        assert_eq!(
+            GetRecovery::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+    }
 }
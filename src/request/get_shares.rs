@@ -1,12 +1,16 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+};
 
+use bc_components::XID;
 use bc_envelope::prelude::*;
 use gstp::prelude::*;
 
 use crate::{
-    Error, GET_SHARES_FUNCTION, RECEIPT_PARAM, RECEIPT_PARAM_NAME, Result,
-    receipt::Receipt,
-    util::{Abbrev, FlankedFunction},
+    DepoFunction, Error, GET_SHARES_FUNCTION, RECEIPT_PARAM, RECEIPT_PARAM_NAME,
+    Result, StoredShare, receipt::Receipt,
+    util::{Abbrev, FlankedFunction, check_expression, response_result},
 };
 
 //
@@ -49,6 +53,12 @@ impl TryFrom<Expression> for GetShares {
     type Error = Error;
 
     fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &GET_SHARES_FUNCTION,
+            &[],
+            &[RECEIPT_PARAM],
+        )?;
         let receipts = expression
             .objects_for_parameter(RECEIPT_PARAM)
             .into_iter()
@@ -63,6 +73,11 @@ impl TryFrom<Expression> for GetShares {
     }
 }
 
+impl DepoFunction for GetShares {
+    const FUNCTION: Function = GET_SHARES_FUNCTION;
+    type Response = GetSharesResult;
+}
+
 impl std::fmt::Display for GetShares {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -77,26 +92,143 @@ impl std::fmt::Display for GetShares {
 // Response
 //
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct GetSharesResult(HashMap<Receipt, ByteString>);
+/// The shares returned by `getShares`, each with whatever metadata the depo
+/// holds for it.
+#[derive(Clone)]
+pub struct GetSharesResult {
+    receipt_to_share: HashMap<Receipt, StoredShare>,
+    /// `receipt_to_share` without the metadata, built when first asked for.
+    receipt_to_data: OnceLock<HashMap<Receipt, ByteString>>,
+}
 
 impl GetSharesResult {
     pub fn new(receipt_to_data: HashMap<Receipt, ByteString>) -> Self {
-        Self(receipt_to_data)
+        Self::new_with_shares(
+            receipt_to_data
+                .into_iter()
+                .map(|(receipt, data)| (receipt, StoredShare::new(data)))
+                .collect(),
+        )
+    }
+
+    pub fn new_with_shares(
+        receipt_to_share: HashMap<Receipt, StoredShare>,
+    ) -> Self {
+        Self { receipt_to_share, receipt_to_data: OnceLock::new() }
     }
 
-    pub fn receipt_to_data(&self) -> &HashMap<Receipt, ByteString> { &self.0 }
+    pub fn receipt_to_share(&self) -> &HashMap<Receipt, StoredShare> {
+        &self.receipt_to_share
+    }
+
+    /// The data of each share, without its metadata.
+    pub fn receipt_to_data(&self) -> &HashMap<Receipt, ByteString> {
+        self.receipt_to_data.get_or_init(|| {
+            self.receipt_to_share
+                .iter()
+                .map(|(receipt, share)| (receipt.clone(), share.data().clone()))
+                .collect()
+        })
+    }
+
+    pub fn share_for_receipt(&self, receipt: &Receipt) -> Option<&StoredShare> {
+        self.receipt_to_share.get(receipt)
+    }
 
     pub fn data_for_receipt(&self, receipt: &Receipt) -> Option<&ByteString> {
-        self.0.get(receipt)
+        self.share_for_receipt(receipt).map(StoredShare::data)
+    }
+
+    /// Checks each returned share against its receipt, and the receipts
+    /// named by `request` against those returned.
+    ///
+    /// Shares returned for receipts `request` did not name are reported as
+    /// unexpected rather than checked, unless it asked for all shares.
+    ///
+    /// `user_id` is the XID the account was created with, which receipts
+    /// are bound to, even after the account is recovered by other keys.
+    /// Salted receipts are checked using the salt in each share's metadata.
+    pub fn verify(
+        &self,
+        user_id: &XID,
+        request: &GetShares,
+    ) -> ShareVerification {
+        let mut verification = ShareVerification::default();
+        let all_shares = request.receipts().is_empty();
+        for (receipt, share) in &self.receipt_to_share {
+            if !all_shares && !request.receipts().contains(receipt) {
+                verification.unexpected.insert(receipt.clone());
+                continue;
+            }
+            let salt = share.metadata().salt();
+            if receipt.verify_with_salt(user_id, salt, share.data()) {
+                verification.matching.insert(receipt.clone());
+            } else {
+                verification.mismatched.insert(receipt.clone());
+            }
+        }
+        verification.missing = request
+            .receipts()
+            .iter()
+            .filter(|receipt| !self.receipt_to_share.contains_key(receipt))
+            .cloned()
+            .collect();
+        verification
+    }
+}
+
+impl PartialEq for GetSharesResult {
+    fn eq(&self, other: &Self) -> bool {
+        self.receipt_to_share == other.receipt_to_share
+    }
+}
+
+impl Eq for GetSharesResult {}
+
+impl std::fmt::Debug for GetSharesResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GetSharesResult")
+            .field("receipt_to_share", &self.receipt_to_share)
+            .finish()
+    }
+}
+
+/// The outcome of checking a `GetSharesResult` against its receipts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShareVerification {
+    matching: HashSet<Receipt>,
+    mismatched: HashSet<Receipt>,
+    missing: HashSet<Receipt>,
+    unexpected: HashSet<Receipt>,
+}
+
+impl ShareVerification {
+    /// Receipts whose shares were returned intact.
+    pub fn matching(&self) -> &HashSet<Receipt> { &self.matching }
+
+    /// Receipts whose returned data does not hash to the receipt.
+    pub fn mismatched(&self) -> &HashSet<Receipt> { &self.mismatched }
+
+    /// Receipts that were requested but not returned.
+    pub fn missing(&self) -> &HashSet<Receipt> { &self.missing }
+
+    /// Receipts that were returned but not requested.
+    pub fn unexpected(&self) -> &HashSet<Receipt> { &self.unexpected }
+
+    /// Returns `true` if every requested share, and nothing else, was
+    /// returned intact.
+    pub fn is_complete(&self) -> bool {
+        self.mismatched.is_empty()
+            && self.missing.is_empty()
+            && self.unexpected.is_empty()
     }
 }
 
 impl From<GetSharesResult> for Envelope {
     fn from(value: GetSharesResult) -> Self {
         let mut result = known_values::OK_VALUE.to_envelope();
-        for (receipt, data) in value.0 {
-            result = result.add_assertion(receipt, data);
+        for (receipt, share) in value.receipt_to_share {
+            result = result.add_assertion(receipt, share);
         }
         result
     }
@@ -106,7 +238,7 @@ impl TryFrom<Envelope> for GetSharesResult {
     type Error = Error;
 
     fn try_from(envelope: Envelope) -> Result<Self> {
-        let mut receipt_to_data = HashMap::new();
+        let mut receipt_to_share = HashMap::new();
         for assertion in envelope.assertions() {
             let receipt =
                 Receipt::try_from(assertion.try_predicate().map_err(|e| {
@@ -128,17 +260,9 @@ impl TryFrom<Envelope> for GetSharesResult {
                         e
                     ),
                 })?;
-            let data = ByteString::try_from(object).map_err(|e| {
-                Error::InvalidEnvelope {
-                    message: format!(
-                        "failed to convert object to ByteString: {}",
-                        e
-                    ),
-                }
-            })?;
-            receipt_to_data.insert(receipt, data);
+            receipt_to_share.insert(receipt, StoredShare::try_from(object)?);
         }
-        Ok(Self::new(receipt_to_data))
+        Ok(Self::new_with_shares(receipt_to_share))
     }
 }
 
@@ -146,31 +270,26 @@ impl TryFrom<SealedResponse> for GetSharesResult {
     type Error = Error;
 
     fn try_from(response: SealedResponse) -> Result<Self> {
-        response.result()?.clone().try_into()
+        response_result(&response)?.try_into()
     }
 }
 
 impl std::fmt::Display for GetSharesResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let abbrevable: HashMap<Receipt, ByteString> = self
-            .receipt_to_data()
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
         f.write_fmt(format_args!(
             "{} OK {}",
             "getShares".flanked_function(),
-            abbrevable.abbrev()
+            self.receipt_to_data().abbrev()
         ))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use bc_components::XID;
     use indoc::indoc;
 
     use super::*;
+    use crate::ShareMetadata;
 
     fn user_id() -> XID {
         XID::from_data_ref(hex_literal::hex!(
@@ -241,4 +360,128 @@ mod tests {
         let decoded = GetSharesResult::try_from(response_envelope).unwrap();
         assert_eq!(response, decoded);
     }
+
+    #[test]
+    fn test_response_with_metadata() {
+        bc_envelope::register_tags();
+
+        let metadata = ShareMetadata::new().with_label("Group 1").with_size(6);
+        let share = StoredShare::new_with_metadata(data_1(), metadata);
+        let receipt_to_share = vec![(receipt_1(), share)].into_iter().collect();
+        let response = GetSharesResult::new_with_shares(receipt_to_share);
+        let response_envelope = response.to_envelope();
+        // println!("{}", response_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(response_envelope.format(), indoc! {r#"
+            'OK' [
+                Bytes(32) [
+                    'isA': "Receipt"
+                ]
+                : Bytes(6) [
+                    "label": "Group 1"
+                    "size": 6
+                ]
+            ]
+        "#}.trim());
+        let decoded =
+            GetSharesResult::try_from(response_envelope.clone()).unwrap();
+        assert_eq!(response, decoded);
+        let share = decoded.share_for_receipt(&receipt_1()).unwrap();
+        assert_eq!(share.metadata().label(), Some("Group 1"));
+        assert_eq!(share.metadata().size(), Some(6));
+        assert_eq!(decoded.data_for_receipt(&receipt_1()), Some(&data_1()));
+
+        // The data map is built from the shares when first asked for, which
+        // leaves the result equal to one that has not built it.
+        assert_eq!(decoded.receipt_to_data()[&receipt_1()], data_1());
+        assert_eq!(response, decoded);
+    }
+
+    #[test]
+    fn test_verify() {
+        let request = GetShares::new(vec![receipt_1(), receipt_2()]);
+
+        let response = GetSharesResult::new(
+            vec![(receipt_1(), data_1()), (receipt_2(), data_2())]
+                .into_iter()
+                .collect(),
+        );
+        let verification = response.verify(&user_id(), &request);
+        assert!(verification.is_complete());
+        assert_eq!(verification.matching().len(), 2);
+
+        // The depo swapped one share's data and withheld the other.
+        let response = GetSharesResult::new(
+            vec![(receipt_1(), data_2())].into_iter().collect(),
+        );
+        let verification = response.verify(&user_id(), &request);
+        assert!(!verification.is_complete());
+        assert!(verification.matching().is_empty());
+        assert!(verification.mismatched().contains(&receipt_1()));
+        assert!(verification.missing().contains(&receipt_2()));
+
+        // Nothing is missing when every share was requested.
+        let verification =
+            response.verify(&user_id(), &GetShares::new_all_shares());
+        assert!(verification.missing().is_empty());
+
+        // The depo returned a share that was not asked for.
+        let request = GetShares::new(vec![receipt_1()]);
+        let response = GetSharesResult::new(
+            vec![(receipt_1(), data_1()), (receipt_2(), data_2())]
+                .into_iter()
+                .collect(),
+        );
+        let verification = response.verify(&user_id(), &request);
+        assert!(!verification.is_complete());
+        assert!(verification.matching().contains(&receipt_1()));
+        assert!(verification.unexpected().contains(&receipt_2()));
+        assert!(verification.missing().is_empty());
+        let verification =
+            response.verify(&user_id(), &GetShares::new_all_shares());
+        assert!(verification.is_complete());
+    }
+
+    #[test]
+    fn test_verify_salted() {
+        let salt = bc_components::Salt::from_data(b"saltsalt");
+        let receipt = Receipt::new_salted(user_id(), &salt, data_1());
+        let request = GetShares::new(vec![receipt.clone()]);
+
+        let share = StoredShare::new_with_metadata(
+            data_1(),
+            crate::ShareMetadata::new().with_salt(salt),
+        );
+        let response = GetSharesResult::new_with_shares(
+            vec![(receipt.clone(), share)].into_iter().collect(),
+        );
+        assert!(response.verify(&user_id(), &request).is_complete());
+
+        // Without its salt, a salted receipt cannot be checked.
+        let response = GetSharesResult::new(
+            vec![(receipt.clone(), data_1())].into_iter().collect(),
+        );
+        let verification = response.verify(&user_id(), &request);
+        assert!(verification.mismatched().contains(&receipt));
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::DELETE_SHARES_FUNCTION)
+            .with_parameter(RECEIPT_PARAM, receipt_1());
+        assert!(matches!(
+            GetShares::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(GET_SHARES_FUNCTION)
+            .with_parameter(RECEIPT_PARAM, receipt_1())
+            .with_parameter(crate::DATA_PARAM, ByteString::from(b"data"));
+        assert_eq!(
+            GetShares::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: data"
+        );
+    }
 }
@@ -0,0 +1,173 @@
+use bc_components::XIDProvider;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::{
+    DepoFunction, Error, GET_XID_DOCUMENT_FUNCTION, Result,
+    util::{FlankedFunction, check_expression, response_result},
+};
+
+//
+// Request
+//
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetXIDDocument();
+
+impl GetXIDDocument {
+    pub fn new() -> Self { Self() }
+}
+
+impl Default for GetXIDDocument {
+    fn default() -> Self { Self::new() }
+}
+
+impl From<GetXIDDocument> for Expression {
+    fn from(_: GetXIDDocument) -> Self {
+        Expression::new(GET_XID_DOCUMENT_FUNCTION)
+    }
+}
+
+impl TryFrom<Expression> for GetXIDDocument {
+    type Error = Error;
+
+    fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(&expression, &GET_XID_DOCUMENT_FUNCTION, &[], &[])?;
+        Ok(Self::new())
+    }
+}
+
+impl DepoFunction for GetXIDDocument {
+    const FUNCTION: Function = GET_XID_DOCUMENT_FUNCTION;
+    type Response = GetXIDDocumentResult;
+}
+
+impl std::fmt::Display for GetXIDDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", "getXIDDocument".flanked_function()))
+    }
+}
+
+//
+// Response
+//
+
+/// The XID document the depo holds for the account, as last set by
+/// `updateXIDDocument` or recovery. Its provenance mark, if it has one, is
+/// carried with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetXIDDocumentResult(XIDDocument);
+
+impl GetXIDDocumentResult {
+    pub fn new(xid_document: XIDDocument) -> Self { Self(xid_document) }
+
+    pub fn xid_document(&self) -> &XIDDocument { &self.0 }
+}
+
+impl From<GetXIDDocumentResult> for Envelope {
+    fn from(value: GetXIDDocumentResult) -> Self { value.0.into_envelope() }
+}
+
+impl TryFrom<Envelope> for GetXIDDocumentResult {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let xid_document = XIDDocument::try_from(envelope).map_err(|e| {
+            Error::InvalidEnvelope {
+                message: format!("failed to convert to XIDDocument: {}", e),
+            }
+        })?;
+        Ok(Self::new(xid_document))
+    }
+}
+
+impl TryFrom<SealedResponse> for GetXIDDocumentResult {
+    type Error = Error;
+
+    fn try_from(response: SealedResponse) -> Result<Self> {
+        response_result(&response)?.try_into()
+    }
+}
+
+impl std::fmt::Display for GetXIDDocumentResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} OK: {}",
+            "getXIDDocument".flanked_function(),
+            self.xid_document().xid()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{PrivateKeyBase, PublicKeysProvider};
+    use bc_rand::make_fake_random_number_generator;
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        bc_envelope::register_tags();
+
+        let request = GetXIDDocument::new();
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"getXIDDocument"»
+        "#}.trim());
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = GetXIDDocument::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+    }
+
+    #[test]
+    fn test_response() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let xid_document: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+
+        let response = GetXIDDocumentResult::new(xid_document.clone());
+        let response_envelope = response.to_envelope();
+        // println!("{}", response_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(response_envelope.format(), indoc! {r#"
+            XID(71274df1) [
+                'key': PublicKeys(eb9b1cae, SigningPublicKey(71274df1, SchnorrPublicKey(9022010e)), EncapsulationPublicKey(b4f7059a, X25519PublicKey(b4f7059a))) [
+                    'allow': 'All'
+                ]
+            ]
+        "#}.trim());
+        let decoded =
+            GetXIDDocumentResult::try_from(response_envelope).unwrap();
+        assert_eq!(response, decoded);
+        assert_eq!(decoded.xid_document(), &xid_document);
+        assert!(decoded.xid_document().provenance().is_none());
+
+        assert!(GetXIDDocumentResult::try_from(Envelope::null()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::GET_RECOVERY_FUNCTION);
+        assert!(matches!(
+            GetXIDDocument::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(GET_XID_DOCUMENT_FUNCTION)
+            .with_parameter("extra", "value");
+        assert_eq!(
+            GetXIDDocument::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use bc_envelope::prelude::*;
+use gstp::prelude::*;
+
+use crate::{
+    CURSOR_PARAM, CURSOR_PARAM_NAME, DepoFunction, Error, LIMIT_PARAM,
+    LIMIT_PARAM_NAME, LIST_SHARES_FUNCTION, Result, ShareMetadata,
+    receipt::Receipt,
+    util::{Abbrev, FlankedFunction, check_expression, response_result},
+};
+
+pub const SHARE_PREDICATE: &str = "share";
+pub const NEXT_CURSOR_PREDICATE: &str = "nextCursor";
+
+//
+// Request
+//
+
+/// Lists the receipts of the shares an account holds, with their metadata
+/// but not their data.
+///
+/// The depo returns at most `limit` shares, or fewer if it caps the page
+/// size itself. To fetch the next page, pass the `next_cursor` of the
+/// previous result as `cursor`. Cursors are opaque to the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListShares {
+    limit: Option<usize>,
+    cursor: Option<ByteString>,
+}
+
+impl ListShares {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: impl Into<ByteString>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    pub fn limit(&self) -> Option<usize> { self.limit }
+
+    pub fn cursor(&self) -> Option<&ByteString> { self.cursor.as_ref() }
+}
+
+impl From<ListShares> for Expression {
+    fn from(value: ListShares) -> Self {
+        Expression::new(LIST_SHARES_FUNCTION)
+            .with_optional_parameter(LIMIT_PARAM, value.limit)
+            .with_optional_parameter(CURSOR_PARAM, value.cursor)
+    }
+}
+
+impl TryFrom<Expression> for ListShares {
+    type Error = Error;
+
+    fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &LIST_SHARES_FUNCTION,
+            &[LIMIT_PARAM, CURSOR_PARAM],
+            &[],
+        )?;
+        let limit = expression
+            .extract_optional_object_for_parameter(LIMIT_PARAM)
+            .map_err(|e| Error::InvalidParameter {
+                parameter: LIMIT_PARAM_NAME.to_string(),
+                message: format!("expected an unsigned integer: {}", e),
+            })?;
+        let cursor = expression
+            .extract_optional_object_for_parameter(CURSOR_PARAM)
+            .map_err(|e| Error::InvalidParameter {
+                parameter: CURSOR_PARAM_NAME.to_string(),
+                message: format!("expected bytes: {}", e),
+            })?;
+        Ok(Self { limit, cursor })
+    }
+}
+
+impl DepoFunction for ListShares {
+    const FUNCTION: Function = LIST_SHARES_FUNCTION;
+    type Response = ListSharesResult;
+}
+
+impl std::fmt::Display for ListShares {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limit = self
+            .limit()
+            .map_or_else(|| "<None>".to_string(), |limit| limit.to_string());
+        let cursor = self
+            .cursor()
+            .map_or_else(|| "<None>".to_string(), Abbrev::abbrev);
+        f.write_fmt(format_args!(
+            "{} limit {} cursor {}",
+            "listShares".flanked_function(),
+            limit,
+            cursor
+        ))
+    }
+}
+
+//
+// Response
+//
+
+/// One page of the shares an account holds.
+///
+/// Each share is encoded as its receipt with the share's metadata as
+/// additional assertions:
+///
+/// ```text
+/// 'OK' [
+///     "nextCursor": Bytes(32)
+///     "share": Bytes(32) [
+///         "size": 6
+///         'isA': "Receipt"
+///         "storedAt": 2024-01-01
+///     ]
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListSharesResult {
+    receipt_to_metadata: HashMap<Receipt, ShareMetadata>,
+    next_cursor: Option<ByteString>,
+}
+
+impl ListSharesResult {
+    pub fn new(
+        receipt_to_metadata: HashMap<Receipt, ShareMetadata>,
+        next_cursor: Option<ByteString>,
+    ) -> Self {
+        Self { receipt_to_metadata, next_cursor }
+    }
+
+    pub fn receipt_to_metadata(&self) -> &HashMap<Receipt, ShareMetadata> {
+        &self.receipt_to_metadata
+    }
+
+    pub fn receipts(&self) -> impl Iterator<Item = &Receipt> {
+        self.receipt_to_metadata.keys()
+    }
+
+    pub fn metadata_for_receipt(
+        &self,
+        receipt: &Receipt,
+    ) -> Option<&ShareMetadata> {
+        self.receipt_to_metadata.get(receipt)
+    }
+
+    /// The cursor for the next page, or `None` if this is the last page.
+    pub fn next_cursor(&self) -> Option<&ByteString> {
+        self.next_cursor.as_ref()
+    }
+}
+
+impl From<ListSharesResult> for Envelope {
+    fn from(value: ListSharesResult) -> Self {
+        let mut result = known_values::OK_VALUE.to_envelope();
+        for (receipt, metadata) in value.receipt_to_metadata {
+            result = result.add_assertion(
+                SHARE_PREDICATE,
+                metadata.add_to_envelope(receipt.into_envelope()),
+            );
+        }
+        result.add_optional_assertion(NEXT_CURSOR_PREDICATE, value.next_cursor)
+    }
+}
+
+impl TryFrom<Envelope> for ListSharesResult {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let invalid = |e: bc_envelope::Error| Error::InvalidEnvelope {
+            message: format!("invalid share list: {}", e),
+        };
+        let mut receipt_to_metadata = HashMap::new();
+        for share in envelope.objects_for_predicate(SHARE_PREDICATE) {
+            let receipt = Receipt::try_from(share.clone())?;
+            let metadata = ShareMetadata::from_envelope(&share)?;
+            receipt_to_metadata.insert(receipt, metadata);
+        }
+        let next_cursor = envelope
+            .extract_optional_object_for_predicate(NEXT_CURSOR_PREDICATE)
+            .map_err(invalid)?;
+        Ok(Self::new(receipt_to_metadata, next_cursor))
+    }
+}
+
+impl TryFrom<SealedResponse> for ListSharesResult {
+    type Error = Error;
+
+    fn try_from(response: SealedResponse) -> Result<Self> {
+        response_result(&response)?.try_into()
+    }
+}
+
+impl std::fmt::Display for ListSharesResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let receipts = self.receipts().cloned().collect::<Vec<_>>();
+        let more = if self.next_cursor().is_some() { " more" } else { "" };
+        f.write_fmt(format_args!(
+            "{} OK {}{}",
+            "listShares".flanked_function(),
+            receipts.abbrev(),
+            more
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::XID;
+    use indoc::indoc;
+
+    use super::*;
+
+    fn user_id() -> XID {
+        XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_request() {
+        bc_envelope::register_tags();
+
+        let request = ListShares::new();
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        // println!("{}", request_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"listShares"»
+        "#}.trim());
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = ListShares::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+
+        let request = ListShares::new().with_limit(10).with_cursor(b"cursor");
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        // println!("{}", request_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"listShares"» [
+                ❰"cursor"❱: Bytes(6)
+                ❰"limit"❱: 10
+            ]
+        "#}.trim());
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = ListShares::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+    }
+
+    #[test]
+    fn test_response() {
+        bc_envelope::register_tags();
+
+        let receipt = Receipt::new(user_id(), b"data_1");
+        let metadata = ShareMetadata::new().with_size(6);
+        let receipt_to_metadata =
+            vec![(receipt.clone(), metadata)].into_iter().collect();
+        let response = ListSharesResult::new(
+            receipt_to_metadata,
+            Some(ByteString::from(b"cursor")),
+        );
+        let response_envelope = response.to_envelope();
+        // println!("{}", response_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(response_envelope.format(), indoc! {r#"
+            'OK' [
+                "nextCursor": Bytes(6)
+                "share": Bytes(32) [
+                    'isA': "Receipt"
+                    "size": 6
+                ]
+            ]
+        "#}.trim());
+        let decoded = ListSharesResult::try_from(response_envelope).unwrap();
+        assert_eq!(response, decoded);
+        assert_eq!(
+            decoded.metadata_for_receipt(&receipt).unwrap().size(),
+            Some(6)
+        );
+
+        let response = ListSharesResult::new(HashMap::new(), None);
+        let decoded =
+            ListSharesResult::try_from(response.to_envelope()).unwrap();
+        assert_eq!(response, decoded);
+        assert!(decoded.next_cursor().is_none());
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::GET_SHARES_FUNCTION)
+            .with_parameter(LIMIT_PARAM, 10);
+        assert!(matches!(
+            ListShares::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(LIST_SHARES_FUNCTION)
+            .with_parameter(LIMIT_PARAM, "ten");
+        assert!(matches!(
+            ListShares::try_from(expression),
+            Err(Error::InvalidParameter { .. })
+        ));
+
+        let expression = Expression::new(LIST_SHARES_FUNCTION)
+            .with_parameter(crate::RECEIPT_PARAM, "receipt");
+        assert_eq!(
+            ListShares::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: receipt"
+        );
+    }
+}
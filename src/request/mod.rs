@@ -1,5 +1,11 @@
 pub mod util;
 
+pub mod depo_function;
+pub use depo_function::DepoFunction;
+
+pub mod depo_request;
+pub use depo_request::DepoRequest;
+
 pub mod delete_account;
 pub use delete_account::DeleteAccount;
 
@@ -9,18 +15,30 @@ pub use get_recovery::{GetRecovery, GetRecoveryResult};
 pub mod delete_shares;
 pub use delete_shares::DeleteShares;
 
+pub mod ok_result;
+pub use ok_result::OkResult;
+
 pub mod finish_recovery;
 pub use finish_recovery::FinishRecovery;
 
 pub mod get_shares;
-pub use get_shares::{GetShares, GetSharesResult};
+pub use get_shares::{GetShares, GetSharesResult, ShareVerification};
+
+pub mod get_xid_document;
+pub use get_xid_document::{GetXIDDocument, GetXIDDocumentResult};
+
+pub mod list_shares;
+pub use list_shares::{ListShares, ListSharesResult};
 
 pub mod start_recovery;
-pub use start_recovery::StartRecovery;
+pub use start_recovery::{StartRecovery, StartRecoveryResult};
 
 pub mod store_share;
 pub use store_share::{StoreShare, StoreShareResult};
 
+pub mod store_shares;
+pub use store_shares::{StoreShares, StoreSharesResult};
+
 pub mod update_xid_document;
 pub use update_xid_document::UpdateXIDDocument;
 
@@ -0,0 +1,76 @@
+use bc_envelope::prelude::*;
+use gstp::prelude::*;
+
+use crate::{Error, Result, util::response_result};
+
+//
+// Response
+//
+
+/// The result of a function that returns nothing but `'OK'` on success.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OkResult();
+
+impl OkResult {
+    pub fn new() -> Self { Self() }
+}
+
+impl Default for OkResult {
+    fn default() -> Self { Self::new() }
+}
+
+impl From<OkResult> for Envelope {
+    fn from(_: OkResult) -> Self { known_values::OK_VALUE.to_envelope() }
+}
+
+impl TryFrom<Envelope> for OkResult {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let value: KnownValue =
+            envelope
+                .extract_subject()
+                .map_err(|e| Error::InvalidEnvelope {
+                    message: format!("expected 'OK': {}", e),
+                })?;
+        if value != known_values::OK_VALUE {
+            return Err(Error::InvalidEnvelope {
+                message: format!("expected 'OK', found {}", value),
+            });
+        }
+        Ok(Self::new())
+    }
+}
+
+impl TryFrom<SealedResponse> for OkResult {
+    type Error = Error;
+
+    fn try_from(response: SealedResponse) -> Result<Self> {
+        response_result(&response)?.try_into()
+    }
+}
+
+impl std::fmt::Display for OkResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OK")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response() {
+        bc_envelope::register_tags();
+
+        let response = OkResult::new();
+        let response_envelope = response.to_envelope();
+        assert_eq!(response_envelope.format(), "'OK'");
+        let decoded = OkResult::try_from(response_envelope).unwrap();
+        assert_eq!(response, decoded);
+
+        assert!(OkResult::try_from(Envelope::null()).is_err());
+        assert!(OkResult::try_from(Envelope::new("OK")).is_err());
+    }
+}
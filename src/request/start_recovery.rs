@@ -1,9 +1,10 @@
 use bc_envelope::prelude::*;
+use gstp::prelude::*;
 
 use crate::{
-    Error, RECOVERY_METHOD_PARAM, RECOVERY_METHOD_PARAM_NAME, Result,
-    START_RECOVERY_FUNCTION,
-    util::{Abbrev, FlankedFunction},
+    DepoFunction, Error, OkResult, RECOVERY_METHOD_PARAM,
+    RECOVERY_METHOD_PARAM_NAME, RecoveryMethod, Result, START_RECOVERY_FUNCTION,
+    util::{Abbrev, FlankedFunction, check_expression, response_result},
 };
 
 //
@@ -11,18 +12,18 @@ use crate::{
 //
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct StartRecovery(String);
+pub struct StartRecovery(RecoveryMethod);
 
 impl StartRecovery {
-    pub fn new(recovery: String) -> Self { Self(recovery) }
+    pub fn new(recovery: RecoveryMethod) -> Self { Self(recovery) }
 
-    pub fn recovery(&self) -> &str { self.0.as_ref() }
+    pub fn recovery(&self) -> &RecoveryMethod { &self.0 }
 }
 
 impl From<StartRecovery> for Expression {
     fn from(value: StartRecovery) -> Self {
         Expression::new(START_RECOVERY_FUNCTION)
-            .with_parameter(RECOVERY_METHOD_PARAM, value.0.clone())
+            .with_parameter(RECOVERY_METHOD_PARAM, value.0)
     }
 }
 
@@ -30,16 +31,32 @@ impl TryFrom<Expression> for StartRecovery {
     type Error = Error;
 
     fn try_from(expression: Expression) -> Result<Self> {
-        Ok(Self::new(
-            expression
-                .extract_object_for_parameter(RECOVERY_METHOD_PARAM)
-                .map_err(|_e| Error::MissingParameter {
-                    parameter: RECOVERY_METHOD_PARAM_NAME.to_string(),
-                })?,
-        ))
+        check_expression(
+            &expression,
+            &START_RECOVERY_FUNCTION,
+            &[RECOVERY_METHOD_PARAM],
+            &[],
+        )?;
+        let object = expression
+            .object_for_parameter(RECOVERY_METHOD_PARAM)
+            .map_err(|_e| Error::MissingParameter {
+                parameter: RECOVERY_METHOD_PARAM_NAME.to_string(),
+            })?;
+        let recovery = RecoveryMethod::try_from(object).map_err(|e| {
+            Error::InvalidParameter {
+                parameter: RECOVERY_METHOD_PARAM_NAME.to_string(),
+                message: format!("invalid recovery method: {}", e),
+            }
+        })?;
+        Ok(Self::new(recovery))
     }
 }
 
+impl DepoFunction for StartRecovery {
+    const FUNCTION: Function = START_RECOVERY_FUNCTION;
+    type Response = StartRecoveryResult;
+}
+
 impl std::fmt::Display for StartRecovery {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -50,6 +67,67 @@ impl std::fmt::Display for StartRecovery {
     }
 }
 
+//
+// Response
+//
+
+/// The result of `startRecovery`.
+///
+/// A depo normally delivers the sealed `RecoveryContinuation` out of band to
+/// the recovery method, proving that the requester controls it, and replies
+/// with just `'OK'`. A depo used for testing, or one whose recovery method
+/// is verified some other way, may return the continuation directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartRecoveryResult(Option<Envelope>);
+
+impl StartRecoveryResult {
+    pub fn new(continuation: Option<Envelope>) -> Self { Self(continuation) }
+
+    pub fn continuation(&self) -> Option<&Envelope> { self.0.as_ref() }
+}
+
+impl From<StartRecoveryResult> for Envelope {
+    fn from(value: StartRecoveryResult) -> Self {
+        value.0.unwrap_or_else(|| OkResult::new().into())
+    }
+}
+
+impl TryFrom<Envelope> for StartRecoveryResult {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        if OkResult::try_from(envelope.clone()).is_ok() {
+            Ok(Self::new(None))
+        } else {
+            Ok(Self::new(Some(envelope)))
+        }
+    }
+}
+
+impl TryFrom<SealedResponse> for StartRecoveryResult {
+    type Error = Error;
+
+    fn try_from(response: SealedResponse) -> Result<Self> {
+        response_result(&response)?.try_into()
+    }
+}
+
+impl std::fmt::Display for StartRecoveryResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.continuation() {
+            Some(continuation) => f.write_fmt(format_args!(
+                "{} OK continuation {}",
+                "startRecovery".flanked_function(),
+                continuation.abbrev()
+            )),
+            None => f.write_fmt(format_args!(
+                "{} OK",
+                "startRecovery".flanked_function()
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
@@ -60,7 +138,7 @@ mod tests {
     fn test_request() {
         bc_envelope::register_tags();
 
-        let recovery = "recovery".to_string();
+        let recovery = RecoveryMethod::email("alice@example.com").unwrap();
         let request = StartRecovery::new(recovery);
         let expression: Expression = request.clone().into();
         let request_envelope = expression.to_envelope();
@@ -68,12 +146,75 @@ mod tests {
         #[rustfmt::skip]
         assert_eq!(request_envelope.format(), indoc! {r#"
             «"startRecovery"» [
-                ❰"recoveryMethod"❱: "recovery"
+                ❰"recoveryMethod"❱: "alice@example.com" [
+                    'isA': "RecoveryMethod"
+                    "kind": "email"
+                ]
             ]
         "#}.trim());
         let decoded_expression =
             Expression::try_from(request_envelope).unwrap();
         let decoded = StartRecovery::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
+
+        // Older peers send the recovery method as a plain string.
+        let expression = Expression::new(START_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "alice@example.com");
+        let decoded = StartRecovery::try_from(expression).unwrap();
+        assert_eq!(request, decoded);
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::UPDATE_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery");
+        assert!(matches!(
+            StartRecovery::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(START_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery_1")
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery_2");
+        assert_eq!(
+            StartRecovery::try_from(expression).unwrap_err().to_string(),
+            "duplicate parameter: recoveryMethod"
+        );
+
+        let expression = Expression::new(START_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery")
+            .with_parameter("extra", "value");
+        assert_eq!(
+            StartRecovery::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+
+        let expression = Expression::new(START_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "mailto:alice");
+        assert!(matches!(
+            StartRecovery::try_from(expression),
+            Err(Error::InvalidParameter { .. })
+        ));
+    }
+
+    #[test]
+    fn test_response() {
+        bc_envelope::register_tags();
+
+        let response = StartRecoveryResult::new(None);
+        let response_envelope = response.to_envelope();
+        assert_eq!(response_envelope.format(), "'OK'");
+        let decoded =
+            StartRecoveryResult::try_from(response_envelope).unwrap();
+        assert_eq!(response, decoded);
+
+        let continuation = Envelope::new("continuation").wrap();
+        let response = StartRecoveryResult::new(Some(continuation));
+        let response_envelope = response.to_envelope();
+        let decoded =
+            StartRecoveryResult::try_from(response_envelope).unwrap();
+        assert_eq!(response, decoded);
     }
 }
@@ -2,28 +2,84 @@ use bc_envelope::prelude::*;
 use gstp::prelude::*;
 
 use crate::{
-    DATA_PARAM, DATA_PARAM_NAME, Error, Result, STORE_SHARE_FUNCTION,
-    receipt::Receipt,
-    util::{Abbrev, FlankedFunction},
+    APPLICATION_TAG_PARAM, CONTENT_TYPE_PARAM, DATA_PARAM, DATA_PARAM_NAME,
+    DepoFunction, Error, LABEL_PARAM, Result, STORE_SHARE_FUNCTION,
+    ShareMetadata, receipt::Receipt,
+    share_metadata::{extract_share_data, share_data_envelope},
+    util::{
+        Abbrev, FlankedFunction, bare_name, check_expression, response_result,
+    },
 };
 
 //
 // Request
 //
 
+/// Stores a share, optionally with a label, content type and application
+/// tag the depo will return alongside it.
+///
+/// Only the client-supplied fields of the metadata are sent; the depo
+/// supplies the rest.
+///
+/// Large shares can be sent compressed. The depo decompresses them before
+/// computing the receipt, so receipts are the same either way.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct StoreShare(ByteString);
+pub struct StoreShare {
+    data: ByteString,
+    metadata: ShareMetadata,
+    compressed: bool,
+}
 
 impl StoreShare {
-    pub fn new(data: impl Into<ByteString>) -> Self { Self(data.into()) }
+    pub fn new(data: impl Into<ByteString>) -> Self {
+        Self::new_with_metadata(data, ShareMetadata::new())
+    }
+
+    pub fn new_with_metadata(
+        data: impl Into<ByteString>,
+        metadata: ShareMetadata,
+    ) -> Self {
+        Self {
+            data: data.into(),
+            metadata: metadata.client_fields(),
+            compressed: false,
+        }
+    }
+
+    /// Sends the data as a compressed envelope.
+    pub fn with_compression(mut self) -> Self {
+        self.compressed = true;
+        self
+    }
+
+    /// The uncompressed data.
+    pub fn data(&self) -> &[u8] { self.data.as_ref() }
 
-    pub fn data(&self) -> &[u8] { self.0.as_ref() }
+    pub fn metadata(&self) -> &ShareMetadata { &self.metadata }
+
+    pub fn is_compressed(&self) -> bool { self.compressed }
 }
 
 impl From<StoreShare> for Expression {
     fn from(value: StoreShare) -> Self {
+        let metadata = value.metadata;
         Expression::new(STORE_SHARE_FUNCTION)
-            .with_parameter(DATA_PARAM, value.0)
+            .with_parameter(
+                DATA_PARAM,
+                share_data_envelope(value.data, value.compressed),
+            )
+            .with_optional_parameter(
+                LABEL_PARAM,
+                metadata.label().map(str::to_string),
+            )
+            .with_optional_parameter(
+                CONTENT_TYPE_PARAM,
+                metadata.content_type().map(str::to_string),
+            )
+            .with_optional_parameter(
+                APPLICATION_TAG_PARAM,
+                metadata.application_tag().map(str::to_string),
+            )
     }
 }
 
@@ -31,16 +87,57 @@ impl TryFrom<Expression> for StoreShare {
     type Error = Error;
 
     fn try_from(expression: Expression) -> Result<Self> {
-        Ok(Self::new(
+        check_expression(
+            &expression,
+            &STORE_SHARE_FUNCTION,
+            &[
+                DATA_PARAM,
+                LABEL_PARAM,
+                CONTENT_TYPE_PARAM,
+                APPLICATION_TAG_PARAM,
+            ],
+            &[],
+        )?;
+        let object = expression
+            .object_for_parameter(DATA_PARAM)
+            .map_err(|_e| Error::MissingParameter {
+                parameter: DATA_PARAM_NAME.to_string(),
+            })?;
+        let compressed = object.subject().is_compressed();
+        let data = extract_share_data(object).map_err(|e| {
+            Error::InvalidParameter {
+                parameter: DATA_PARAM_NAME.to_string(),
+                message: e.to_string(),
+            }
+        })?;
+        let string_param = |parameter: Parameter| -> Result<Option<String>> {
             expression
-                .extract_object_for_parameter::<ByteString>(DATA_PARAM)
-                .map_err(|_e| Error::MissingParameter {
-                    parameter: DATA_PARAM_NAME.to_string(),
-                })?,
-        ))
+                .extract_optional_object_for_parameter(parameter.clone())
+                .map_err(|e| Error::InvalidParameter {
+                    parameter: bare_name(parameter.name()),
+                    message: format!("expected a string: {}", e),
+                })
+        };
+        let mut metadata = ShareMetadata::new();
+        if let Some(label) = string_param(LABEL_PARAM)? {
+            metadata = metadata.with_label(label);
+        }
+        if let Some(content_type) = string_param(CONTENT_TYPE_PARAM)? {
+            metadata = metadata.with_content_type(content_type);
+        }
+        if let Some(application_tag) = string_param(APPLICATION_TAG_PARAM)? {
+            metadata = metadata.with_application_tag(application_tag);
+        }
+        let request = Self::new_with_metadata(data, metadata);
+        Ok(if compressed { request.with_compression() } else { request })
     }
 }
 
+impl DepoFunction for StoreShare {
+    const FUNCTION: Function = STORE_SHARE_FUNCTION;
+    type Response = StoreShareResult;
+}
+
 impl std::fmt::Display for StoreShare {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -80,7 +177,7 @@ impl TryFrom<SealedResponse> for StoreShareResult {
     type Error = Error;
 
     fn try_from(response: SealedResponse) -> Result<Self> {
-        response.result()?.clone().try_into()
+        response_result(&response)?.try_into()
     }
 }
 
@@ -120,6 +217,59 @@ mod tests {
             Expression::try_from(request_envelope).unwrap();
         let decoded = StoreShare::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
+
+        let metadata = ShareMetadata::new()
+            .with_label("Group 1")
+            .with_content_type("application/x-sskr")
+            .with_application_tag("wallet")
+            .with_size(4);
+        let request = StoreShare::new_with_metadata(data, metadata);
+        assert_eq!(request.metadata().size(), None);
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        // println!("{}", request_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"storeShare"» [
+                ❰"applicationTag"❱: "wallet"
+                ❰"contentType"❱: "application/x-sskr"
+                ❰"data"❱: Bytes(4)
+                ❰"label"❱: "Group 1"
+            ]
+        "#}.trim());
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = StoreShare::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+    }
+
+    #[test]
+    fn test_compressed_request() {
+        bc_envelope::register_tags();
+
+        let data = "descriptor ".repeat(100).into_bytes();
+        let request = StoreShare::new(data.clone()).with_compression();
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        // println!("{}", request_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"storeShare"» [
+                ❰"data"❱: COMPRESSED
+            ]
+        "#}.trim());
+        let uncompressed: Expression = StoreShare::new(data.clone()).into();
+        assert!(
+            request_envelope.to_cbor_data().len()
+                < uncompressed.to_envelope().to_cbor_data().len()
+        );
+
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = StoreShare::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+        assert!(decoded.is_compressed());
+        assert_eq!(decoded.data(), data.as_slice());
     }
 
     #[test]
@@ -144,4 +294,46 @@ mod tests {
         let decoded = StoreShareResult::try_from(result_envelope).unwrap();
         assert_eq!(result, decoded);
     }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::GET_SHARES_FUNCTION)
+            .with_parameter(DATA_PARAM, ByteString::from(b"data"));
+        assert!(matches!(
+            StoreShare::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(STORE_SHARE_FUNCTION)
+            .with_parameter(DATA_PARAM, ByteString::from(b"data_1"))
+            .with_parameter(DATA_PARAM, ByteString::from(b"data_2"));
+        assert_eq!(
+            StoreShare::try_from(expression).unwrap_err().to_string(),
+            "duplicate parameter: data"
+        );
+
+        let expression = Expression::new(STORE_SHARE_FUNCTION)
+            .with_parameter(DATA_PARAM, ByteString::from(b"data"))
+            .with_parameter("extra", "value");
+        assert_eq!(
+            StoreShare::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+
+        let expression = Expression::new(STORE_SHARE_FUNCTION);
+        assert!(matches!(
+            StoreShare::try_from(expression),
+            Err(Error::MissingParameter { .. })
+        ));
+
+        let expression = Expression::new(STORE_SHARE_FUNCTION)
+            .with_parameter(DATA_PARAM, ByteString::from(b"data"))
+            .with_parameter(LABEL_PARAM, 1);
+        assert!(matches!(
+            StoreShare::try_from(expression),
+            Err(Error::InvalidParameter { .. })
+        ));
+    }
 }
@@ -0,0 +1,341 @@
+use bc_envelope::prelude::*;
+use gstp::prelude::*;
+
+use crate::{
+    DepoFunction, Error, RECEIPT_PARAM_NAME, Result, SHARE_PARAM,
+    SHARE_PARAM_NAME, STORE_SHARES_FUNCTION, StoreShare, StoredShare,
+    receipt::Receipt,
+    util::{Abbrev, FlankedFunction, check_expression, response_result},
+};
+
+pub const INDEX_PREDICATE: &str = "index";
+pub const RECEIPT_PREDICATE: &str = "receipt";
+
+//
+// Request
+//
+
+/// Stores several shares in one request, such as all the shares of an SSKR
+/// split.
+///
+/// The batch is all-or-nothing: the depo either stores every share and
+/// returns a receipt for each, in the order the shares were given, or
+/// stores none of them and returns an error.
+///
+/// Each share is sent as a `share` parameter carrying its position in the
+/// batch, since parameters are unordered:
+///
+/// ```text
+/// «"storeShares"» [
+///     ❰"share"❱: Bytes(6) [
+///         "index": 0
+///         "label": "Group 1"
+///     ]
+///     ❰"share"❱: Bytes(6) [
+///         "index": 1
+///     ]
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreShares(Vec<StoreShare>);
+
+impl StoreShares {
+    pub fn new(shares: impl IntoIterator<Item = StoreShare>) -> Self {
+        Self(shares.into_iter().collect())
+    }
+
+    pub fn new_from_data<I, T>(iterable: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<ByteString>,
+    {
+        Self::new(iterable.into_iter().map(StoreShare::new))
+    }
+
+    pub fn shares(&self) -> &[StoreShare] { &self.0 }
+}
+
+impl From<StoreShares> for Expression {
+    fn from(value: StoreShares) -> Self {
+        let mut expression = Expression::new(STORE_SHARES_FUNCTION);
+        for (index, share) in value.0.into_iter().enumerate() {
+            let compressed = share.is_compressed();
+            let share = StoredShare::new_with_metadata(
+                share.data(),
+                share.metadata().clone(),
+            );
+            let share =
+                if compressed { share.with_compression() } else { share };
+            expression = expression.with_parameter(
+                SHARE_PARAM,
+                share.into_envelope().add_assertion(INDEX_PREDICATE, index),
+            );
+        }
+        expression
+    }
+}
+
+impl TryFrom<Expression> for StoreShares {
+    type Error = Error;
+
+    fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &STORE_SHARES_FUNCTION,
+            &[],
+            &[SHARE_PARAM],
+        )?;
+        let invalid = |message: String| Error::InvalidParameter {
+            parameter: SHARE_PARAM_NAME.to_string(),
+            message,
+        };
+        let objects = expression.objects_for_parameter(SHARE_PARAM);
+        if objects.is_empty() {
+            return Err(Error::MissingParameter {
+                parameter: SHARE_PARAM_NAME.to_string(),
+            });
+        }
+        let mut indexed = objects
+            .into_iter()
+            .map(|object| {
+                let index: usize = object
+                    .extract_object_for_predicate(INDEX_PREDICATE)
+                    .map_err(|e| invalid(format!("missing index: {}", e)))?;
+                let share = StoredShare::try_from(object)
+                    .map_err(|e| invalid(e.to_string()))?;
+                Ok((index, share))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        indexed.sort_by_key(|(index, _)| *index);
+        check_indexes(indexed.iter().map(|(index, _)| *index))
+            .map_err(invalid)?;
+        Ok(Self::new(indexed.into_iter().map(|(_, share)| {
+            let request = StoreShare::new_with_metadata(
+                share.data().clone(),
+                share.metadata().clone(),
+            );
+            if share.is_compressed() {
+                request.with_compression()
+            } else {
+                request
+            }
+        })))
+    }
+}
+
+/// Checks that sorted `indexes` number a batch from zero without gaps or
+/// repeats.
+fn check_indexes(
+    indexes: impl Iterator<Item = usize>,
+) -> std::result::Result<(), String> {
+    for (expected, index) in indexes.enumerate() {
+        if index != expected {
+            return Err(format!("expected index {}, found {}", expected, index));
+        }
+    }
+    Ok(())
+}
+
+impl DepoFunction for StoreShares {
+    const FUNCTION: Function = STORE_SHARES_FUNCTION;
+    type Response = StoreSharesResult;
+}
+
+impl std::fmt::Display for StoreShares {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let data = self
+            .shares()
+            .iter()
+            .map(|share| ByteString::from(share.data()))
+            .collect::<Vec<_>>();
+        f.write_fmt(format_args!(
+            "{} {}",
+            "storeShares".flanked_function(),
+            data.abbrev()
+        ))
+    }
+}
+
+//
+// Response
+//
+
+/// The receipts for a stored batch, in the order the shares were given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreSharesResult(Vec<Receipt>);
+
+impl StoreSharesResult {
+    pub fn new(receipts: Vec<Receipt>) -> Self { Self(receipts) }
+
+    pub fn receipts(&self) -> &[Receipt] { &self.0 }
+}
+
+impl From<StoreSharesResult> for Envelope {
+    fn from(value: StoreSharesResult) -> Self {
+        let mut result = known_values::OK_VALUE.to_envelope();
+        for (index, receipt) in value.0.into_iter().enumerate() {
+            result = result.add_assertion(
+                RECEIPT_PREDICATE,
+                receipt.into_envelope().add_assertion(INDEX_PREDICATE, index),
+            );
+        }
+        result
+    }
+}
+
+impl TryFrom<Envelope> for StoreSharesResult {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let invalid = |message: String| Error::InvalidParameter {
+            parameter: RECEIPT_PARAM_NAME.to_string(),
+            message,
+        };
+        let mut indexed = envelope
+            .objects_for_predicate(RECEIPT_PREDICATE)
+            .into_iter()
+            .map(|object| {
+                let index: usize = object
+                    .extract_object_for_predicate(INDEX_PREDICATE)
+                    .map_err(|e| invalid(format!("missing index: {}", e)))?;
+                Ok((index, Receipt::try_from(object)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        indexed.sort_by_key(|(index, _)| *index);
+        check_indexes(indexed.iter().map(|(index, _)| *index))
+            .map_err(invalid)?;
+        Ok(Self::new(
+            indexed.into_iter().map(|(_, receipt)| receipt).collect(),
+        ))
+    }
+}
+
+impl TryFrom<SealedResponse> for StoreSharesResult {
+    type Error = Error;
+
+    fn try_from(response: SealedResponse) -> Result<Self> {
+        response_result(&response)?.try_into()
+    }
+}
+
+impl std::fmt::Display for StoreSharesResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} OK receipts {}",
+            "storeShares".flanked_function(),
+            self.0.abbrev()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::XID;
+    use indoc::indoc;
+
+    use super::*;
+    use crate::ShareMetadata;
+
+    fn user_id() -> XID {
+        XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_request() {
+        bc_envelope::register_tags();
+
+        let metadata = ShareMetadata::new().with_label("Group 1");
+        let request = StoreShares::new([
+            StoreShare::new_with_metadata(b"data_1", metadata),
+            StoreShare::new(b"data_2"),
+        ]);
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        // println!("{}", request_envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"storeShares"» [
+                ❰"share"❱: Bytes(6) [
+                    "index": 0
+                    "label": "Group 1"
+                ]
+                ❰"share"❱: Bytes(6) [
+                    "index": 1
+                ]
+            ]
+        "#}.trim());
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = StoreShares::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+        assert_eq!(decoded.shares()[1].data(), b"data_2");
+    }
+
+    #[test]
+    fn test_response() {
+        bc_envelope::register_tags();
+
+        let receipts = (0..3)
+            .map(|i| Receipt::new(user_id(), format!("data_{}", i)))
+            .collect::<Vec<_>>();
+        let response = StoreSharesResult::new(receipts.clone());
+        let response_envelope = response.to_envelope();
+        let decoded =
+            StoreSharesResult::try_from(response_envelope.clone()).unwrap();
+        assert_eq!(response, decoded);
+        assert_eq!(decoded.receipts(), receipts.as_slice());
+
+        let response = StoreSharesResult::new(receipts[..1].to_vec());
+        // println!("{}", response.to_envelope().format());
+        #[rustfmt::skip]
+        assert_eq!(response.to_envelope().format(), indoc! {r#"
+            'OK' [
+                "receipt": Bytes(32) [
+                    'isA': "Receipt"
+                    "index": 0
+                ]
+            ]
+        "#}.trim());
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(STORE_SHARES_FUNCTION);
+        assert!(matches!(
+            StoreShares::try_from(expression),
+            Err(Error::MissingParameter { .. })
+        ));
+
+        let share = |index: usize| {
+            Envelope::new(ByteString::from(b"data"))
+                .add_assertion(INDEX_PREDICATE, index)
+        };
+        let expression = Expression::new(STORE_SHARES_FUNCTION)
+            .with_parameter(SHARE_PARAM, share(0))
+            .with_parameter(SHARE_PARAM, share(2));
+        assert_eq!(
+            StoreShares::try_from(expression).unwrap_err().to_string(),
+            "invalid parameter value for 'share': expected index 1, found 2"
+        );
+
+        let unindexed = Envelope::new(ByteString::from(b"data"));
+        let expression = Expression::new(STORE_SHARES_FUNCTION)
+            .with_parameter(SHARE_PARAM, unindexed);
+        assert!(matches!(
+            StoreShares::try_from(expression),
+            Err(Error::InvalidParameter { .. })
+        ));
+
+        let expression = Expression::new(crate::STORE_SHARE_FUNCTION)
+            .with_parameter(SHARE_PARAM, share(0));
+        assert!(matches!(
+            StoreShares::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+    }
+}
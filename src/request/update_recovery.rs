@@ -1,9 +1,10 @@
 use bc_envelope::prelude::*;
 
 use crate::{
-    Error, RECOVERY_METHOD_PARAM, RECOVERY_METHOD_PARAM_NAME, Result,
+    DepoFunction, Error, OkResult, RECOVERY_METHOD_PARAM,
+    RECOVERY_METHOD_PARAM_NAME, RecoveryMethod, Result,
     UPDATE_RECOVERY_FUNCTION,
-    util::{Abbrev, FlankedFunction},
+    util::{Abbrev, FlankedFunction, check_expression},
 };
 
 //
@@ -11,21 +12,17 @@ use crate::{
 //
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct UpdateRecovery(Option<String>);
+pub struct UpdateRecovery(Option<RecoveryMethod>);
 
 impl UpdateRecovery {
-    pub fn new(recovery: Option<String>) -> Self { Self(recovery) }
+    pub fn new(recovery: Option<RecoveryMethod>) -> Self { Self(recovery) }
 
-    pub fn recovery(&self) -> Option<&String> { self.0.as_ref() }
+    pub fn recovery(&self) -> Option<&RecoveryMethod> { self.0.as_ref() }
 }
 
 impl From<UpdateRecovery> for Expression {
     fn from(value: UpdateRecovery) -> Self {
-        let method = if let Some(recovery) = value.0.clone() {
-            recovery.to_envelope()
-        } else {
-            Envelope::null()
-        };
+        let method = value.0.map_or_else(Envelope::null, Envelope::from);
         Expression::new(UPDATE_RECOVERY_FUNCTION)
             .with_parameter(RECOVERY_METHOD_PARAM, method)
     }
@@ -35,6 +32,12 @@ impl TryFrom<Expression> for UpdateRecovery {
     type Error = Error;
 
     fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &UPDATE_RECOVERY_FUNCTION,
+            &[RECOVERY_METHOD_PARAM],
+            &[],
+        )?;
         let recovery_object = expression
             .object_for_parameter(RECOVERY_METHOD_PARAM)
             .map_err(|_e| Error::MissingParameter {
@@ -43,12 +46,10 @@ impl TryFrom<Expression> for UpdateRecovery {
         let recovery = if recovery_object.is_null() {
             None
         } else {
-            Some(recovery_object.extract_subject().map_err(|e| {
-                Error::InvalidEnvelope {
-                    message: format!(
-                        "failed to extract recovery method: {}",
-                        e
-                    ),
+            Some(RecoveryMethod::try_from(recovery_object).map_err(|e| {
+                Error::InvalidParameter {
+                    parameter: RECOVERY_METHOD_PARAM_NAME.to_string(),
+                    message: format!("invalid recovery method: {}", e),
                 }
             })?)
         };
@@ -56,6 +57,11 @@ impl TryFrom<Expression> for UpdateRecovery {
     }
 }
 
+impl DepoFunction for UpdateRecovery {
+    const FUNCTION: Function = UPDATE_RECOVERY_FUNCTION;
+    type Response = OkResult;
+}
+
 impl std::fmt::Display for UpdateRecovery {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -76,7 +82,7 @@ mod tests {
     fn test_request() {
         bc_envelope::register_tags();
 
-        let recovery = "recovery".to_string();
+        let recovery = RecoveryMethod::phone("+15550109999").unwrap();
 
         let request = UpdateRecovery::new(Some(recovery));
         let expression: Expression = request.clone().into();
@@ -85,12 +91,57 @@ mod tests {
         #[rustfmt::skip]
         assert_eq!(request_envelope.format(), indoc! {r#"
             «"updateRecovery"» [
-                ❰"recoveryMethod"❱: "recovery"
+                ❰"recoveryMethod"❱: "+15550109999" [
+                    'isA': "RecoveryMethod"
+                    "kind": "phone"
+                ]
             ]
         "#}.trim());
         let decoded_expression =
             Expression::try_from(request_envelope).unwrap();
         let decoded = UpdateRecovery::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
+
+        let request = UpdateRecovery::new(None);
+        let expression: Expression = request.clone().into();
+        let request_envelope = expression.to_envelope();
+        #[rustfmt::skip]
+        assert_eq!(request_envelope.format(), indoc! {r#"
+            «"updateRecovery"» [
+                ❰"recoveryMethod"❱: null
+            ]
+        "#}.trim());
+        let decoded_expression =
+            Expression::try_from(request_envelope).unwrap();
+        let decoded = UpdateRecovery::try_from(decoded_expression).unwrap();
+        assert_eq!(request, decoded);
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let expression = Expression::new(crate::START_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery");
+        assert!(matches!(
+            UpdateRecovery::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(UPDATE_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery_1")
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery_2");
+        assert_eq!(
+            UpdateRecovery::try_from(expression).unwrap_err().to_string(),
+            "duplicate parameter: recoveryMethod"
+        );
+
+        let expression = Expression::new(UPDATE_RECOVERY_FUNCTION)
+            .with_parameter(RECOVERY_METHOD_PARAM, "recovery")
+            .with_parameter("extra", "value");
+        assert_eq!(
+            UpdateRecovery::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
     }
 }
@@ -1,29 +1,142 @@
-use bc_components::XIDProvider;
+use bc_components::{Digest, Signature, Signer, Verifier, XID, XIDProvider};
 use bc_envelope::prelude::*;
 use bc_xid::XIDDocument;
 
 use crate::{
-    Error, NEW_XID_DOCUMENT_PARAM, NEW_XID_DOCUMENT_PARAM_NAME, Result,
-    UPDATE_XID_DOCUMENT_FUNCTION, util::FlankedFunction,
+    DepoError, DepoFunction, Error, NEW_XID_DOCUMENT_PARAM,
+    NEW_XID_DOCUMENT_PARAM_NAME, OkResult, PREVIOUS_DIGEST_PARAM,
+    PREVIOUS_DIGEST_PARAM_NAME, PROOF_PARAM, PROOF_PARAM_NAME, Result,
+    UPDATE_XID_DOCUMENT_FUNCTION,
+    util::{FlankedFunction, check_expression},
 };
 
 //
 // Request
 //
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct UpdateXIDDocument(XIDDocument);
+/// Replaces the account's XID document, for example to rotate its keys.
+///
+/// The request can carry the digest of the document it expects to replace,
+/// so an update based on a stale read is refused, and a proof: a signature
+/// by a key of the account's current document over the rest of the request
+/// and the account's XID, showing its controller approved the update. As
+/// the proof is checked against the current document rather than the new
+/// one, it keeps working after a rotation removes the inception key.
+/// Servers check both with `validate` before accepting the update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateXIDDocument {
+    new_xid_document: XIDDocument,
+    previous_digest: Option<Digest>,
+    proof: Option<Signature>,
+}
 
 impl UpdateXIDDocument {
-    pub fn new(new_xid_document: XIDDocument) -> Self { Self(new_xid_document) }
+    pub fn new(new_xid_document: XIDDocument) -> Self {
+        Self { new_xid_document, previous_digest: None, proof: None }
+    }
+
+    /// Expects the account's current document to be `previous`.
+    ///
+    /// Any proof must be added after this, as it covers the digest.
+    pub fn with_previous_xid_document(self, previous: &XIDDocument) -> Self {
+        self.with_previous_digest(Self::xid_document_digest(previous))
+    }
+
+    pub fn with_previous_digest(mut self, previous_digest: Digest) -> Self {
+        self.previous_digest = Some(previous_digest);
+        self
+    }
+
+    /// Signs the request for the account identified by `account`, the XID
+    /// of its current document, with one of that document's keys.
+    pub fn with_proof(
+        mut self,
+        account: &XID,
+        signer: &dyn Signer,
+    ) -> Result<Self> {
+        let digest = self.proof_digest(account);
+        self.proof = Some(signer.sign(digest.data())?);
+        Ok(self)
+    }
+
+    /// Attaches a proof made elsewhere, such as by a hardware signer, over
+    /// `proof_digest`.
+    pub fn with_proof_signature(mut self, proof: Signature) -> Self {
+        self.proof = Some(proof);
+        self
+    }
+
+    pub fn new_xid_document(&self) -> &XIDDocument { &self.new_xid_document }
+
+    pub fn previous_digest(&self) -> Option<&Digest> {
+        self.previous_digest.as_ref()
+    }
+
+    pub fn proof(&self) -> Option<&Signature> { self.proof.as_ref() }
 
-    pub fn new_xid_document(&self) -> &XIDDocument { &self.0 }
+    /// The digest by which `previous_digest` identifies a document.
+    pub fn xid_document_digest(xid_document: &XIDDocument) -> Digest {
+        xid_document.clone().into_envelope().digest()
+    }
+
+    /// The digest the proof signs: that of the request without its proof,
+    /// bound to `account`, so a proof can't be presented to another account.
+    pub fn proof_digest(&self, account: &XID) -> Digest {
+        let unsigned = Self { proof: None, ..self.clone() };
+        Digest::from_digests(&[
+            Digest::from_image(account.data()),
+            Expression::from(unsigned).into_envelope().digest(),
+        ])
+    }
+
+    /// Checks the request against the account's `current` document before a
+    /// server accepts it.
+    ///
+    /// Fails with `DepoError::XIDDocumentChanged` if the request expects a
+    /// different document than `current`, and with
+    /// `DepoError::InvalidSignature` if its proof is not signed by a key of
+    /// `current` for the account it identifies. A missing proof is refused
+    /// only if `require_proof` is set.
+    pub fn validate(
+        &self,
+        current: &XIDDocument,
+        require_proof: bool,
+    ) -> Result<()> {
+        if let Some(previous_digest) = &self.previous_digest
+            && previous_digest != &Self::xid_document_digest(current)
+        {
+            return Err(Error::Remote(DepoError::XIDDocumentChanged));
+        }
+        match &self.proof {
+            Some(proof) => {
+                let digest = self.proof_digest(&current.xid());
+                let verified = current.keys().iter().any(|key| {
+                    key.public_keys().verify(proof, digest.data())
+                });
+                if !verified {
+                    return Err(Error::Remote(DepoError::InvalidSignature));
+                }
+            }
+            None if require_proof => {
+                return Err(Error::Remote(DepoError::InvalidRequest(
+                    "missing proof".to_string(),
+                )));
+            }
+            None => {}
+        }
+        Ok(())
+    }
 }
 
 impl From<UpdateXIDDocument> for Expression {
     fn from(value: UpdateXIDDocument) -> Self {
         Expression::new(UPDATE_XID_DOCUMENT_FUNCTION)
-            .with_parameter(NEW_XID_DOCUMENT_PARAM, value.0)
+            .with_parameter(NEW_XID_DOCUMENT_PARAM, value.new_xid_document)
+            .with_optional_parameter(
+                PREVIOUS_DIGEST_PARAM,
+                value.previous_digest,
+            )
+            .with_optional_parameter(PROOF_PARAM, value.proof)
     }
 }
 
@@ -31,6 +144,12 @@ impl TryFrom<Expression> for UpdateXIDDocument {
     type Error = Error;
 
     fn try_from(expression: Expression) -> Result<Self> {
+        check_expression(
+            &expression,
+            &UPDATE_XID_DOCUMENT_FUNCTION,
+            &[NEW_XID_DOCUMENT_PARAM, PREVIOUS_DIGEST_PARAM, PROOF_PARAM],
+            &[],
+        )?;
         let object = expression
             .object_for_parameter(NEW_XID_DOCUMENT_PARAM)
             .map_err(|_e| Error::MissingParameter {
@@ -42,24 +161,48 @@ impl TryFrom<Expression> for UpdateXIDDocument {
                 message: format!("failed to convert to XIDDocument: {}", e),
             }
         })?;
-        Ok(Self::new(new_xid_document))
+        let previous_digest = expression
+            .extract_optional_object_for_parameter(PREVIOUS_DIGEST_PARAM)
+            .map_err(|e| Error::InvalidParameter {
+                parameter: PREVIOUS_DIGEST_PARAM_NAME.to_string(),
+                message: format!("expected a digest: {}", e),
+            })?;
+        let proof = expression
+            .extract_optional_object_for_parameter(PROOF_PARAM)
+            .map_err(|e| Error::InvalidParameter {
+                parameter: PROOF_PARAM_NAME.to_string(),
+                message: format!("expected a signature: {}", e),
+            })?;
+        Ok(Self { new_xid_document, previous_digest, proof })
     }
 }
 
+impl DepoFunction for UpdateXIDDocument {
+    const FUNCTION: Function = UPDATE_XID_DOCUMENT_FUNCTION;
+    type Response = OkResult;
+}
+
 impl std::fmt::Display for UpdateXIDDocument {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
             "{} new {}",
             "updateXIDDocument".flanked_function(),
             self.new_xid_document().xid()
-        ))
+        ))?;
+        if self.proof.is_some() {
+            f.write_str(" (signed)")?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use bc_components::{PrivateKeyBase, PublicKeysProvider};
+    use bc_components::{
+        PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider,
+    };
     use bc_rand::make_fake_random_number_generator;
+    use bc_xid::Key;
     use indoc::indoc;
 
     use super::*;
@@ -91,4 +234,135 @@ mod tests {
         let decoded = UpdateXIDDocument::try_from(decoded_expression).unwrap();
         assert_eq!(request, decoded);
     }
+
+    #[test]
+    fn test_invalid_request() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let xid_document_1: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+        let xid_document_2: XIDDocument =
+            PrivateKeyBase::new_using(&mut rng).public_keys().into();
+
+        let expression = Expression::new(crate::STORE_SHARE_FUNCTION)
+            .with_parameter(NEW_XID_DOCUMENT_PARAM, xid_document_1.clone());
+        assert!(matches!(
+            UpdateXIDDocument::try_from(expression),
+            Err(Error::UnexpectedFunction { .. })
+        ));
+
+        let expression = Expression::new(UPDATE_XID_DOCUMENT_FUNCTION)
+            .with_parameter(NEW_XID_DOCUMENT_PARAM, xid_document_1.clone())
+            .with_parameter(NEW_XID_DOCUMENT_PARAM, xid_document_2);
+        assert_eq!(
+            UpdateXIDDocument::try_from(expression).unwrap_err().to_string(),
+            "duplicate parameter: newXIDDocument"
+        );
+
+        let expression = Expression::new(UPDATE_XID_DOCUMENT_FUNCTION)
+            .with_parameter(NEW_XID_DOCUMENT_PARAM, xid_document_1)
+            .with_parameter("extra", "value");
+        assert_eq!(
+            UpdateXIDDocument::try_from(expression).unwrap_err().to_string(),
+            "unexpected parameter: extra"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let inception_keys = PrivateKeyBase::new_using(&mut rng);
+        let new_keys = PrivateKeyBase::new_using(&mut rng);
+        let current: XIDDocument = inception_keys.public_keys().into();
+        let account = current.xid();
+
+        // Rotate to a document with a new key and without the inception key.
+        let mut rotated = current.clone();
+        rotated
+            .add_key(Key::new_allow_all(new_keys.public_keys()))
+            .unwrap();
+        rotated.remove_inception_key().unwrap();
+        assert_eq!(rotated.xid(), account);
+
+        let request = UpdateXIDDocument::new(rotated.clone())
+            .with_previous_xid_document(&current)
+            .with_proof(&account, &inception_keys.private_keys())
+            .unwrap();
+        let expression: Expression = request.clone().into();
+        let decoded = UpdateXIDDocument::try_from(expression).unwrap();
+        assert_eq!(request, decoded);
+        decoded.validate(&current, true).unwrap();
+
+        // The account's document changed since the client read it.
+        assert!(matches!(
+            decoded.validate(&rotated, true),
+            Err(Error::Remote(DepoError::XIDDocumentChanged))
+        ));
+
+        // The proof covers the previous digest.
+        let tampered = decoded.with_previous_digest(
+            UpdateXIDDocument::xid_document_digest(&rotated),
+        );
+        assert!(matches!(
+            tampered.validate(&rotated, true),
+            Err(Error::Remote(DepoError::InvalidSignature))
+        ));
+
+        // Only a key of the current document can approve the update, not
+        // one it merely adds.
+        let request = UpdateXIDDocument::new(rotated.clone())
+            .with_proof(&account, &new_keys.private_keys())
+            .unwrap();
+        assert!(matches!(
+            request.validate(&current, false),
+            Err(Error::Remote(DepoError::InvalidSignature))
+        ));
+
+        // A proof made for one account is no good for another, even one the
+        // same key controls.
+        let other: XIDDocument = {
+            let mut other = XIDDocument::from_xid(XID::from_data([7; 32]));
+            other
+                .add_key(Key::new_allow_all(inception_keys.public_keys()))
+                .unwrap();
+            other
+        };
+        let request = UpdateXIDDocument::new(rotated.clone())
+            .with_proof(&account, &inception_keys.private_keys())
+            .unwrap();
+        assert!(matches!(
+            request.validate(&other, false),
+            Err(Error::Remote(DepoError::InvalidSignature))
+        ));
+
+        // Once the inception key is gone, the new key approves the next
+        // update and the inception key no longer can.
+        let mut next = rotated.clone();
+        next.add_key(Key::new_allow_all(
+            PrivateKeyBase::new_using(&mut rng).public_keys(),
+        ))
+        .unwrap();
+        let request = UpdateXIDDocument::new(next.clone())
+            .with_previous_xid_document(&rotated)
+            .with_proof(&account, &new_keys.private_keys())
+            .unwrap();
+        request.validate(&rotated, true).unwrap();
+        let request = UpdateXIDDocument::new(next.clone())
+            .with_proof(&account, &inception_keys.private_keys())
+            .unwrap();
+        assert!(matches!(
+            request.validate(&rotated, true),
+            Err(Error::Remote(DepoError::InvalidSignature))
+        ));
+
+        let request = UpdateXIDDocument::new(next);
+        request.validate(&rotated, false).unwrap();
+        assert!(matches!(
+            request.validate(&rotated, true),
+            Err(Error::Remote(DepoError::InvalidRequest(_)))
+        ));
+    }
 }
@@ -2,8 +2,72 @@ use std::collections::{HashMap, HashSet};
 
 use bc_components::{ARID, PrivateKeyBase, PublicKeys};
 use bc_envelope::prelude::*;
+use gstp::prelude::*;
 
-use crate::Receipt;
+use crate::{DepoError, Error, Receipt, RecoveryMethod, Result};
+
+/// Returns the result carried by a response, or the depo's reason for
+/// refusing the request as `Error::Remote`.
+pub fn response_result(response: &SealedResponse) -> Result<Envelope> {
+    if response.is_err() {
+        let error = DepoError::from_response_error(response.error()?);
+        return Err(Error::Remote(error));
+    }
+    Ok(response.result()?.clone())
+}
+
+/// Checks that `expression` invokes `function` and carries no parameters
+/// other than those listed. Parameters in `single` may appear at most once,
+/// while those in `repeated` may appear any number of times. Presence of
+/// required parameters is left to the caller.
+pub fn check_expression(
+    expression: &Expression,
+    function: &Function,
+    single: &[Parameter],
+    repeated: &[Parameter],
+) -> Result<()> {
+    if expression.function() != function {
+        return Err(Error::UnexpectedFunction {
+            expected: bare_name(function.name()),
+            found: bare_name(expression.function().name()),
+        });
+    }
+    let mut seen: Vec<Parameter> = Vec::new();
+    for assertion in expression.to_envelope().assertions() {
+        let predicate =
+            assertion.try_predicate().map_err(|e| Error::InvalidEnvelope {
+                message: format!("failed to extract parameter: {}", e),
+            })?;
+        let parameter: Parameter =
+            predicate
+                .extract_subject()
+                .map_err(|e| Error::InvalidEnvelope {
+                    message: format!("invalid parameter: {}", e),
+                })?;
+        if single.contains(&parameter) {
+            if seen.contains(&parameter) {
+                return Err(Error::DuplicateParameter {
+                    parameter: bare_name(parameter.name()),
+                });
+            }
+            seen.push(parameter);
+        } else if !repeated.contains(&parameter) {
+            return Err(Error::UnexpectedParameter {
+                parameter: bare_name(parameter.name()),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The name of a function or parameter without the quotes put around names
+/// that are not known values.
+pub fn bare_name(name: String) -> String {
+    match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(bare) => bare.to_string(),
+        None => name,
+    }
+}
 
 pub fn prefix(s: &str, len: usize) -> String { s.chars().take(len).collect() }
 
@@ -118,6 +182,16 @@ impl Abbrev for Option<&str> {
     fn abbrev(&self) -> String { abbreviate_opt_string(self.as_deref()) }
 }
 
+impl Abbrev for RecoveryMethod {
+    fn abbrev(&self) -> String { self.value().abbrev() }
+}
+
+impl Abbrev for Option<&RecoveryMethod> {
+    fn abbrev(&self) -> String {
+        abbreviate_opt_string(self.map(RecoveryMethod::value))
+    }
+}
+
 impl Abbrev for ARID {
     fn abbrev(&self) -> String { self.ur_string().suffix(8).flanked_abbrev() }
 }
@@ -0,0 +1,699 @@
+use bc_components::{PrivateKeys, SigningPublicKey, XID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::{
+    DeleteAccount, DeleteShares, DepoError, DepoRequest, Error, FinishRecovery,
+    FreshnessPolicy, GetRecovery, GetRecoveryResult, GetShares, GetSharesResult,
+    GetXIDDocument, GetXIDDocumentResult, ListShares, ListSharesResult,
+    OkResult, ReplayCache, Result, StartRecovery, StartRecoveryResult,
+    StoreShare, StoreShareResult, StoreShares, StoreSharesResult,
+    UpdateRecovery, UpdateXIDDocument,
+};
+
+/// The sender of a request: the key that signed it, which has been
+/// verified, and the XID document it carried, which has not.
+///
+/// Anyone can send a document claiming any XID and list their own key in
+/// it, so the claim holds only if the signing key is the inception key of
+/// the claimed XID (`is_self_certified`) or a key of the XID document the
+/// depo already holds for it (`is_key_of`).
+#[derive(Debug, Clone)]
+pub struct Sender {
+    xid_document: XIDDocument,
+    signing_key: SigningPublicKey,
+}
+
+impl Sender {
+    pub fn new(
+        xid_document: XIDDocument,
+        signing_key: SigningPublicKey,
+    ) -> Self {
+        Self { xid_document, signing_key }
+    }
+
+    pub fn xid_document(&self) -> &XIDDocument { &self.xid_document }
+
+    pub fn signing_key(&self) -> &SigningPublicKey { &self.signing_key }
+
+    /// The XID the sender claims, which is not checked.
+    pub fn claimed_xid(&self) -> XID { self.xid_document.xid() }
+
+    /// Whether the request was signed by the inception key of the XID the
+    /// sender claims.
+    pub fn is_self_certified(&self) -> bool {
+        self.xid_document.is_inception_signing_key(&self.signing_key)
+    }
+
+    /// Whether the request was signed by one of the keys of `xid_document`.
+    pub fn is_key_of(&self, xid_document: &XIDDocument) -> bool {
+        xid_document.keys().iter().any(|key| {
+            key.public_keys().signing_public_key() == &self.signing_key
+        })
+    }
+}
+
+/// The functions a depo server implements, one method per function.
+///
+/// Each method receives the `Sender` of the request. Only its signing key
+/// is verified, so handlers must check it against the account before
+/// acting on the XID it claims.
+pub trait DepoHandler {
+    fn store_share(
+        &mut self,
+        sender: &Sender,
+        request: StoreShare,
+    ) -> Result<StoreShareResult>;
+
+    /// Stores every share in the batch or, if any cannot be stored, none of
+    /// them.
+    fn store_shares(
+        &mut self,
+        sender: &Sender,
+        request: StoreShares,
+    ) -> Result<StoreSharesResult>;
+
+    fn get_shares(
+        &mut self,
+        sender: &Sender,
+        request: GetShares,
+    ) -> Result<GetSharesResult>;
+
+    fn list_shares(
+        &mut self,
+        sender: &Sender,
+        request: ListShares,
+    ) -> Result<ListSharesResult>;
+
+    fn delete_shares(
+        &mut self,
+        sender: &Sender,
+        request: DeleteShares,
+    ) -> Result<OkResult>;
+
+    fn update_xid_document(
+        &mut self,
+        sender: &Sender,
+        request: UpdateXIDDocument,
+    ) -> Result<OkResult>;
+
+    fn get_xid_document(
+        &mut self,
+        sender: &Sender,
+        request: GetXIDDocument,
+    ) -> Result<GetXIDDocumentResult>;
+
+    fn update_recovery(
+        &mut self,
+        sender: &Sender,
+        request: UpdateRecovery,
+    ) -> Result<OkResult>;
+
+    fn get_recovery(
+        &mut self,
+        sender: &Sender,
+        request: GetRecovery,
+    ) -> Result<GetRecoveryResult>;
+
+    fn start_recovery(
+        &mut self,
+        sender: &Sender,
+        request: StartRecovery,
+    ) -> Result<StartRecoveryResult>;
+
+    fn finish_recovery(
+        &mut self,
+        sender: &Sender,
+        request: FinishRecovery,
+    ) -> Result<OkResult>;
+
+    fn delete_account(
+        &mut self,
+        sender: &Sender,
+        request: DeleteAccount,
+    ) -> Result<OkResult>;
+
+    /// Called with an error the sender is told of only as
+    /// `DepoError::Internal`, so its details can be kept on the depo. Writes
+    /// it to standard error unless overridden, such as to send it to the
+    /// server's log.
+    fn report_internal_error(&mut self, error: &Error) {
+        eprintln!("depo: internal error: {}", error);
+    }
+}
+
+/// Opens a sealed request addressed to the depo, passes it to `handler`, and
+/// seals the result or error back to the sender. Errors are sent as a
+/// `DepoError`, so handlers should return `Error::Remote` for refusals the
+/// sender can act on. Any other failure is sent as `DepoError::Internal`,
+/// and passed to `DepoHandler::report_internal_error`.
+///
+/// The request's signature is checked when it is opened, against the key
+/// its sender's XID document names. Whether that key may act for the XID
+/// the sender claims is left to `handler`; see `Sender`. The date is not:
+/// the expiry the sender sets is sealed in a continuation only the sender
+/// can open, so use `dispatch_checked` to refuse stale requests. If the
+/// request cannot be opened there is nobody to reply to, so the error is
+/// returned instead of a response.
+pub fn dispatch(
+    handler: &mut impl DepoHandler,
+    request_envelope: &Envelope,
+    private_keys: &PrivateKeys,
+    xid_document: &XIDDocument,
+) -> Result<Envelope> {
+    let (request, sender) = open_request(request_envelope, private_keys)?;
+    let result = handle(handler, &sender, request.body().clone())
+        .map_err(|error| depo_error(handler, error));
+    seal_response(&request, result, private_keys, xid_document)
+}
+
+/// Like `dispatch`, but also refuses requests dated outside `freshness`'s
+/// window with `DepoError::StaleRequest`, and replay-protected requests
+/// whose ID is already in `replay_cache` with `DepoError::ReplayedRequest`.
+/// See `DepoRequest::is_replay_protected`.
+///
+/// A replay-protected request's ID is recorded only once `handler` has
+/// carried it out, so a request refused by the handler may be sent again.
+pub fn dispatch_checked(
+    handler: &mut impl DepoHandler,
+    request_envelope: &Envelope,
+    private_keys: &PrivateKeys,
+    xid_document: &XIDDocument,
+    freshness: &FreshnessPolicy,
+    replay_cache: &mut impl ReplayCache,
+) -> Result<Envelope> {
+    let (request, sender) = open_request(request_envelope, private_keys)?;
+    let result = check_request(&request, freshness, replay_cache, &Date::now())
+        .and_then(|expires| {
+            let result = handle(handler, &sender, request.body().clone())?;
+            if let Some(expires) = expires {
+                replay_cache.record(request.id(), expires);
+            }
+            Ok(result)
+        })
+        .map_err(|error| depo_error(handler, error));
+    seal_response(&request, result, private_keys, xid_document)
+}
+
+/// Opens a sealed request, returning it with its sender. The request is
+/// verified against the verification key of the sender's XID document, so
+/// that is the key that signed it.
+fn open_request(
+    request_envelope: &Envelope,
+    private_keys: &PrivateKeys,
+) -> Result<(SealedRequest, Sender)> {
+    let request = SealedRequest::try_from_envelope(
+        request_envelope,
+        None,
+        Some(Date::now()),
+        private_keys,
+    )?;
+    let signing_key = request
+        .sender()
+        .verification_key()
+        .ok_or(gstp::Error::SenderMissingVerificationKey)?
+        .clone();
+    let sender = Sender::new(request.sender().clone(), signing_key);
+    Ok((request, sender))
+}
+
+/// Checks that `request` is fresh and not a replay, returning when its ID
+/// may be forgotten if it must be recorded once carried out.
+fn check_request(
+    request: &SealedRequest,
+    freshness: &FreshnessPolicy,
+    replay_cache: &mut impl ReplayCache,
+    now: &Date,
+) -> Result<Option<Date>> {
+    let expires = freshness.check(request, now)?;
+    let body = DepoRequest::try_from(request.body().clone())?;
+    if !body.is_replay_protected() {
+        return Ok(None);
+    }
+    if replay_cache.contains(&request.id(), now) {
+        return Err(Error::Remote(DepoError::ReplayedRequest));
+    }
+    Ok(Some(expires))
+}
+
+/// Classifies `error` for the sender, reporting it to `handler` if the
+/// sender is not told its details.
+fn depo_error(handler: &mut impl DepoHandler, error: Error) -> DepoError {
+    let depo_error = DepoError::from(&error);
+    if depo_error == DepoError::Internal {
+        handler.report_internal_error(&error);
+    }
+    depo_error
+}
+
+fn seal_response(
+    request: &SealedRequest,
+    result: std::result::Result<Envelope, DepoError>,
+    private_keys: &PrivateKeys,
+    xid_document: &XIDDocument,
+) -> Result<Envelope> {
+    let response = match result {
+        Ok(result) => {
+            SealedResponse::new_success(request.id(), xid_document.clone())
+                .with_result(result)
+        }
+        Err(error) => {
+            SealedResponse::new_failure(request.id(), xid_document.clone())
+                .with_error(error)
+        }
+    }
+    .with_peer_continuation(request.peer_continuation());
+    Ok(response.to_envelope(None, Some(private_keys), Some(request.sender()))?)
+}
+
+fn handle(
+    handler: &mut impl DepoHandler,
+    sender: &Sender,
+    body: Expression,
+) -> Result<Envelope> {
+    Ok(match DepoRequest::try_from(body)? {
+        DepoRequest::DeleteAccount(request) => {
+            handler.delete_account(sender, request)?.into()
+        }
+        DepoRequest::DeleteShares(request) => {
+            handler.delete_shares(sender, request)?.into()
+        }
+        DepoRequest::FinishRecovery(request) => {
+            handler.finish_recovery(sender, request)?.into()
+        }
+        DepoRequest::GetRecovery(request) => {
+            handler.get_recovery(sender, request)?.into()
+        }
+        DepoRequest::GetShares(request) => {
+            handler.get_shares(sender, request)?.into()
+        }
+        DepoRequest::GetXIDDocument(request) => {
+            handler.get_xid_document(sender, request)?.into()
+        }
+        DepoRequest::ListShares(request) => {
+            handler.list_shares(sender, request)?.into()
+        }
+        DepoRequest::StartRecovery(request) => {
+            handler.start_recovery(sender, request)?.into()
+        }
+        DepoRequest::StoreShare(request) => {
+            handler.store_share(sender, request)?.into()
+        }
+        DepoRequest::StoreShares(request) => {
+            handler.store_shares(sender, request)?.into()
+        }
+        DepoRequest::UpdateRecovery(request) => {
+            handler.update_recovery(sender, request)?.into()
+        }
+        DepoRequest::UpdateXIDDocument(request) => {
+            handler.update_xid_document(sender, request)?.into()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    use bc_components::{
+        ARID, PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider, XID,
+        XIDProvider,
+    };
+    use bc_rand::make_fake_random_number_generator;
+    use bc_xid::Key;
+
+    use super::*;
+    use crate::{
+        DepoClient, InProcessTransport, MemoryReplayCache, Receipt,
+        RecoveryMethod,
+    };
+
+    /// Keeps shares and recovery methods, fails internally on `listShares`,
+    /// and refuses everything else.
+    #[derive(Default)]
+    struct TestHandler {
+        shares: HashMap<Receipt, ByteString>,
+        recovery: HashMap<XID, RecoveryMethod>,
+        internal_errors: Vec<String>,
+    }
+
+    fn unsupported() -> Error { Error::Remote(DepoError::Unsupported) }
+
+    /// Accounts are never rotated, so only their inception keys may use
+    /// them.
+    fn account(sender: &Sender) -> Result<XID> {
+        if !sender.is_self_certified() {
+            return Err(Error::Remote(DepoError::UnknownAccount));
+        }
+        Ok(sender.claimed_xid())
+    }
+
+    impl DepoHandler for TestHandler {
+        fn store_share(
+            &mut self,
+            sender: &Sender,
+            request: StoreShare,
+        ) -> Result<StoreShareResult> {
+            let receipt = Receipt::new(account(sender)?, request.data());
+            self.shares
+                .insert(receipt.clone(), request.data().to_vec().into());
+            Ok(StoreShareResult::new(receipt))
+        }
+
+        fn store_shares(
+            &mut self,
+            _sender: &Sender,
+            _request: StoreShares,
+        ) -> Result<StoreSharesResult> {
+            Err(unsupported())
+        }
+
+        fn get_shares(
+            &mut self,
+            _sender: &Sender,
+            request: GetShares,
+        ) -> Result<GetSharesResult> {
+            Ok(GetSharesResult::new(
+                request
+                    .receipts()
+                    .iter()
+                    .filter_map(|receipt| {
+                        self.shares
+                            .get(receipt)
+                            .map(|data| (receipt.clone(), data.clone()))
+                    })
+                    .collect(),
+            ))
+        }
+
+        fn list_shares(
+            &mut self,
+            _sender: &Sender,
+            _request: ListShares,
+        ) -> Result<ListSharesResult> {
+            Err(Error::Transport { message: "disk full".to_string() })
+        }
+
+        fn delete_shares(
+            &mut self,
+            _sender: &Sender,
+            _request: DeleteShares,
+        ) -> Result<OkResult> {
+            Err(unsupported())
+        }
+
+        fn update_xid_document(
+            &mut self,
+            _sender: &Sender,
+            _request: UpdateXIDDocument,
+        ) -> Result<OkResult> {
+            Err(unsupported())
+        }
+
+        fn get_xid_document(
+            &mut self,
+            sender: &Sender,
+            _request: GetXIDDocument,
+        ) -> Result<GetXIDDocumentResult> {
+            account(sender)?;
+            Ok(GetXIDDocumentResult::new(sender.xid_document().clone()))
+        }
+
+        fn update_recovery(
+            &mut self,
+            sender: &Sender,
+            request: UpdateRecovery,
+        ) -> Result<OkResult> {
+            let id = account(sender)?;
+            match request.recovery() {
+                Some(recovery) => self.recovery.insert(id, recovery.clone()),
+                None => self.recovery.remove(&id),
+            };
+            Ok(OkResult::new())
+        }
+
+        fn get_recovery(
+            &mut self,
+            sender: &Sender,
+            _request: GetRecovery,
+        ) -> Result<GetRecoveryResult> {
+            let id = account(sender)?;
+            Ok(GetRecoveryResult::new(self.recovery.get(&id).cloned()))
+        }
+
+        fn start_recovery(
+            &mut self,
+            _sender: &Sender,
+            _request: StartRecovery,
+        ) -> Result<StartRecoveryResult> {
+            Err(unsupported())
+        }
+
+        fn finish_recovery(
+            &mut self,
+            _sender: &Sender,
+            _request: FinishRecovery,
+        ) -> Result<OkResult> {
+            Err(unsupported())
+        }
+
+        fn delete_account(
+            &mut self,
+            _sender: &Sender,
+            _request: DeleteAccount,
+        ) -> Result<OkResult> {
+            Err(unsupported())
+        }
+
+        fn report_internal_error(&mut self, error: &Error) {
+            self.internal_errors.push(error.to_string());
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_private_keys = depo_keys.private_keys();
+        let depo_xid_document: XIDDocument = depo_keys.public_keys().into();
+
+        let handler = Rc::new(RefCell::new(TestHandler::default()));
+        let transport = {
+            let handler = handler.clone();
+            let depo_xid_document = depo_xid_document.clone();
+            InProcessTransport::new(move |envelope| {
+                dispatch(
+                    &mut *handler.borrow_mut(),
+                    &envelope,
+                    &depo_private_keys,
+                    &depo_xid_document,
+                )
+            })
+        };
+        let client = DepoClient::new_with_private_key_base(
+            &user_keys,
+            depo_xid_document,
+            transport,
+        );
+
+        let receipt = client.store_share(b"data").unwrap();
+        let result = client.get_shares(vec![receipt.clone()]).unwrap();
+        assert_eq!(result.data_for_receipt(&receipt).unwrap().data(), b"data");
+        assert_eq!(handler.borrow().shares.len(), 1);
+
+        let xid_document = client.get_xid_document().unwrap();
+        assert_eq!(&xid_document, client.xid_document());
+
+        assert_eq!(client.get_recovery().unwrap(), None);
+        let recovery = RecoveryMethod::recovery_code("recovery").unwrap();
+        client.update_recovery(Some(recovery.clone())).unwrap();
+        assert_eq!(client.get_recovery().unwrap(), Some(recovery));
+
+        assert!(matches!(
+            client.delete_account(),
+            Err(Error::Remote(DepoError::Unsupported))
+        ));
+
+        // Internal failures reach the sender without their details, which
+        // are reported to the handler instead.
+        assert!(matches!(
+            client.list_shares(None, None),
+            Err(Error::Remote(DepoError::Internal))
+        ));
+        assert_eq!(
+            handler.borrow().internal_errors,
+            vec!["transport failed: disk full".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_forged_sender() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let mallory_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_xid_document: XIDDocument = depo_keys.public_keys().into();
+        let user_xid_document: XIDDocument = user_keys.public_keys().into();
+
+        // The handler is given the key that signed the request, which is
+        // Mallory's, not the inception key of the XID the document claims.
+        let mut forged = XIDDocument::from_xid(user_xid_document.xid());
+        forged
+            .add_key(Key::new_allow_all(mallory_keys.public_keys()))
+            .unwrap();
+        let request = SealedRequest::new_with_body(
+            GetRecovery::new().into(),
+            ARID::new(),
+            forged,
+        )
+        .to_envelope(
+            None,
+            Some(&mallory_keys.private_keys()),
+            Some(&depo_xid_document),
+        )
+        .unwrap();
+        let response = dispatch(
+            &mut TestHandler::default(),
+            &request,
+            &depo_keys.private_keys(),
+            &depo_xid_document,
+        )
+        .unwrap();
+        assert_eq!(
+            response_error(&response, &mallory_keys),
+            Some(DepoError::UnknownAccount)
+        );
+    }
+
+    /// Seals `body` from the holder of `sender_keys` to the depo.
+    fn sealed_request(
+        body: Expression,
+        date: Option<Date>,
+        sender_keys: &PrivateKeyBase,
+        depo_xid_document: &XIDDocument,
+    ) -> Envelope {
+        let sender: XIDDocument = sender_keys.public_keys().into();
+        let request = SealedRequest::new_with_body(body, ARID::new(), sender);
+        let request = match date {
+            Some(date) => request.with_date(date),
+            None => request,
+        };
+        request
+            .to_envelope(
+                None,
+                Some(&sender_keys.private_keys()),
+                Some(depo_xid_document),
+            )
+            .unwrap()
+    }
+
+    /// The error a response carries, if any.
+    fn response_error(
+        envelope: &Envelope,
+        sender_keys: &PrivateKeyBase,
+    ) -> Option<DepoError> {
+        let response = SealedResponse::try_from_encrypted_envelope(
+            envelope,
+            None,
+            None,
+            &sender_keys.private_keys(),
+        )
+        .unwrap();
+        response
+            .is_err()
+            .then(|| DepoError::from_response_error(response.error().unwrap()))
+    }
+
+    #[test]
+    fn test_dispatch_checked() {
+        bc_envelope::register_tags();
+
+        let mut rng = make_fake_random_number_generator();
+        let user_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_keys = PrivateKeyBase::new_using(&mut rng);
+        let depo_private_keys = depo_keys.private_keys();
+        let depo_xid_document: XIDDocument = depo_keys.public_keys().into();
+
+        let mut handler = TestHandler::default();
+        let freshness = FreshnessPolicy::default();
+        let mut replay_cache = MemoryReplayCache::new();
+        let mut send = |envelope: &Envelope| {
+            let response = dispatch_checked(
+                &mut handler,
+                envelope,
+                &depo_private_keys,
+                &depo_xid_document,
+                &freshness,
+                &mut replay_cache,
+            )
+            .unwrap();
+            response_error(&response, &user_keys)
+        };
+
+        // A replay-protected request is carried out once, and refused when
+        // replayed.
+        let store_share = sealed_request(
+            StoreShare::new(b"data").into(),
+            Some(Date::now()),
+            &user_keys,
+            &depo_xid_document,
+        );
+        assert_eq!(send(&store_share), None);
+        assert_eq!(send(&store_share), Some(DepoError::ReplayedRequest));
+
+        // So is a request to update the recovery method.
+        let recovery = RecoveryMethod::recovery_code("recovery").unwrap();
+        let update_recovery = sealed_request(
+            UpdateRecovery::new(Some(recovery.clone())).into(),
+            Some(Date::now()),
+            &user_keys,
+            &depo_xid_document,
+        );
+        assert_eq!(send(&update_recovery), None);
+        assert_eq!(send(&update_recovery), Some(DepoError::ReplayedRequest));
+
+        // A request the handler refuses is not recorded, so it may be sent
+        // again.
+        let start_recovery = sealed_request(
+            StartRecovery::new(recovery).into(),
+            Some(Date::now()),
+            &user_keys,
+            &depo_xid_document,
+        );
+        assert_eq!(send(&start_recovery), Some(DepoError::Unsupported));
+        assert_eq!(send(&start_recovery), Some(DepoError::Unsupported));
+
+        // Other requests may be repeated.
+        let get_recovery = sealed_request(
+            GetRecovery::new().into(),
+            Some(Date::now()),
+            &user_keys,
+            &depo_xid_document,
+        );
+        assert_eq!(send(&get_recovery), None);
+        assert_eq!(send(&get_recovery), None);
+
+        // Requests must be dated within the window.
+        let undated = sealed_request(
+            GetRecovery::new().into(),
+            None,
+            &user_keys,
+            &depo_xid_document,
+        );
+        assert_eq!(send(&undated), Some(DepoError::StaleRequest));
+        let old = sealed_request(
+            GetRecovery::new().into(),
+            Some(Date::from_timestamp(Date::now().timestamp() - 3600.0)),
+            &user_keys,
+            &depo_xid_document,
+        );
+        assert_eq!(send(&old), Some(DepoError::StaleRequest));
+        assert_eq!(replay_cache.len(), 2);
+    }
+}
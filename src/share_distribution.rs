@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+
+use bc_components::{
+    SSKRError, SSKRSecret, SSKRShare, SSKRSpec, XID, XIDProvider,
+    sskr_combine, sskr_generate,
+};
+use bc_xid::XIDDocument;
+
+use crate::{
+    DepoClient, Error, GetShares, GetSharesResult, Receipt, Result,
+    ShareMetadata, StoreShare, StoreShares,
+};
+
+/// The content type of shares stored by a `ShareDistribution`.
+pub const SSKR_CONTENT_TYPE: &str = "application/x-sskr";
+
+/// The size of the metadata SSKR puts before the value of each share.
+const SSKR_METADATA_SIZE: usize = 5;
+
+/// The size of the shortest secret SSKR splits, and so of a share's value.
+const SSKR_MIN_SECRET_SIZE: usize = 16;
+
+/// A depo that holds some of the shares of a split secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepoEndpoint {
+    location: String,
+    xid_document: XIDDocument,
+}
+
+impl DepoEndpoint {
+    pub fn new(location: impl Into<String>, xid_document: XIDDocument) -> Self {
+        Self { location: location.into(), xid_document }
+    }
+
+    /// Where the depo is reached, such as its URL.
+    pub fn location(&self) -> &str { &self.location }
+
+    pub fn xid_document(&self) -> &XIDDocument { &self.xid_document }
+
+    pub fn xid(&self) -> XID { self.xid_document.xid() }
+}
+
+fn invalid_distribution(message: impl Into<String>) -> Error {
+    Error::InvalidShareDistribution { message: message.into() }
+}
+
+/// Checks that `clients` holds one client for each of `endpoints`, in the
+/// same order, each sending to the depo of its endpoint.
+fn check_clients(
+    endpoints: &[DepoEndpoint],
+    clients: &[DepoClient],
+) -> Result<()> {
+    if clients.len() != endpoints.len() {
+        return Err(invalid_distribution(format!(
+            "expected clients for {} depos, got {}",
+            endpoints.len(),
+            clients.len()
+        )));
+    }
+    for (index, (endpoint, client)) in endpoints.iter().zip(clients).enumerate()
+    {
+        let found = client.depo_xid_document().xid();
+        if found != endpoint.xid() {
+            return Err(invalid_distribution(format!(
+                "client for depo {} sends to {}, not {}",
+                index,
+                found,
+                endpoint.xid()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A secret split into SSKR shares and assigned to depos, ready to be
+/// stored.
+///
+/// Shares are dealt to the depos in turn, group by group. A layout in which
+/// any one depo would hold enough shares to meet the thresholds alone is
+/// refused, so recovering the secret always takes more than one depo. Each
+/// depo's shares are stored in one `storeShares` request, and the receipts
+/// it returns make up the `RecoveryPlan`.
+#[derive(Debug, Clone)]
+pub struct ShareDistribution {
+    endpoints: Vec<DepoEndpoint>,
+    shares: Vec<(usize, SSKRShare)>,
+}
+
+impl ShareDistribution {
+    pub fn new(
+        secret: &SSKRSecret,
+        spec: &SSKRSpec,
+        endpoints: Vec<DepoEndpoint>,
+    ) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(invalid_distribution("no depos to store shares with"));
+        }
+        let shares = sskr_generate(spec, secret)?
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, share)| (index % endpoints.len(), share))
+            .collect();
+        let distribution = Self { endpoints, shares };
+        for endpoint in 0..distribution.endpoints.len() {
+            let held = distribution
+                .shares_for(endpoint)
+                .into_iter()
+                .cloned()
+                .collect();
+            if threshold_shares(held).is_some() {
+                return Err(invalid_distribution(format!(
+                    "depo {} would hold enough shares to recover the secret",
+                    endpoint
+                )));
+            }
+        }
+        Ok(distribution)
+    }
+
+    pub fn endpoints(&self) -> &[DepoEndpoint] { &self.endpoints }
+
+    /// The shares assigned to the depo at `endpoint`, in the order they are
+    /// stored.
+    pub fn shares_for(&self, endpoint: usize) -> Vec<&SSKRShare> {
+        self.shares
+            .iter()
+            .filter(|(index, _)| *index == endpoint)
+            .map(|(_, share)| share)
+            .collect()
+    }
+
+    /// The request that stores the shares assigned to the depo at
+    /// `endpoint`.
+    pub fn store_request(&self, endpoint: usize) -> StoreShares {
+        let metadata =
+            ShareMetadata::new().with_content_type(SSKR_CONTENT_TYPE);
+        StoreShares::new(self.shares_for(endpoint).into_iter().map(|share| {
+            StoreShare::new_with_metadata(share.as_bytes(), metadata.clone())
+        }))
+    }
+
+    /// Builds the recovery plan from the receipts each depo returned for
+    /// its `store_request`, given in the order of the endpoints.
+    pub fn recovery_plan(
+        &self,
+        receipts: Vec<Vec<Receipt>>,
+    ) -> Result<RecoveryPlan> {
+        if receipts.len() != self.endpoints.len() {
+            return Err(invalid_distribution(format!(
+                "expected receipts from {} depos, got {}",
+                self.endpoints.len(),
+                receipts.len()
+            )));
+        }
+        let mut shares = Vec::new();
+        for (endpoint, receipts) in receipts.into_iter().enumerate() {
+            let assigned = self.shares_for(endpoint);
+            if receipts.len() != assigned.len() {
+                return Err(invalid_distribution(format!(
+                    "expected {} receipts from depo {}, got {}",
+                    assigned.len(),
+                    endpoint,
+                    receipts.len()
+                )));
+            }
+            for (share, receipt) in assigned.into_iter().zip(receipts) {
+                shares.push(PlannedShare {
+                    endpoint,
+                    group_index: share.group_index(),
+                    member_index: share.member_index(),
+                    receipt,
+                });
+            }
+        }
+        Ok(RecoveryPlan { endpoints: self.endpoints.clone(), shares })
+    }
+
+    /// Stores each depo's shares through the client for that depo, given in
+    /// the order of the endpoints, and returns the recovery plan.
+    ///
+    /// Fails before storing anything if any client sends to a depo other
+    /// than the one at its endpoint.
+    pub fn store(&self, clients: &[DepoClient]) -> Result<RecoveryPlan> {
+        check_clients(&self.endpoints, clients)?;
+        let receipts = clients
+            .iter()
+            .enumerate()
+            .map(|(endpoint, client)| {
+                let result = client.send(self.store_request(endpoint))?;
+                Ok(result.receipts().to_vec())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.recovery_plan(receipts)
+    }
+}
+
+/// Where one share of a split secret is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedShare {
+    endpoint: usize,
+    group_index: usize,
+    member_index: usize,
+    receipt: Receipt,
+}
+
+impl PlannedShare {
+    pub fn new(
+        endpoint: usize,
+        group_index: usize,
+        member_index: usize,
+        receipt: Receipt,
+    ) -> Self {
+        Self { endpoint, group_index, member_index, receipt }
+    }
+
+    /// The index of the depo holding the share in the plan's endpoints.
+    pub fn endpoint(&self) -> usize { self.endpoint }
+
+    pub fn group_index(&self) -> usize { self.group_index }
+
+    pub fn member_index(&self) -> usize { self.member_index }
+
+    pub fn receipt(&self) -> &Receipt { &self.receipt }
+
+    /// Reads share data returned by a depo as this share, refusing data too
+    /// short to be an SSKR share or that is some other share.
+    fn parse(&self, data: &[u8]) -> Result<SSKRShare> {
+        if data.len() < SSKR_METADATA_SIZE + SSKR_MIN_SECRET_SIZE {
+            return Err(SSKRError::ShareLengthInvalid.into());
+        }
+        let share = SSKRShare::from_data(data);
+        if share.group_index() != self.group_index
+            || share.member_index() != self.member_index
+        {
+            return Err(invalid_distribution(format!(
+                "expected share {} of group {}, got share {} of group {}",
+                self.member_index,
+                self.group_index,
+                share.member_index(),
+                share.group_index()
+            )));
+        }
+        Ok(share)
+    }
+}
+
+/// A record of which depo holds each share of a split secret, and the
+/// receipt to retrieve it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryPlan {
+    endpoints: Vec<DepoEndpoint>,
+    shares: Vec<PlannedShare>,
+}
+
+impl RecoveryPlan {
+    pub fn new(
+        endpoints: Vec<DepoEndpoint>,
+        shares: Vec<PlannedShare>,
+    ) -> Self {
+        Self { endpoints, shares }
+    }
+
+    pub fn endpoints(&self) -> &[DepoEndpoint] { &self.endpoints }
+
+    pub fn shares(&self) -> &[PlannedShare] { &self.shares }
+
+    /// The request that retrieves the shares held by the depo at
+    /// `endpoint`.
+    pub fn get_shares_request(&self, endpoint: usize) -> GetShares {
+        GetShares::new(
+            self.shares
+                .iter()
+                .filter(|share| share.endpoint == endpoint)
+                .map(|share| share.receipt.clone()),
+        )
+    }
+
+    /// Combines the planned shares found in `results` back into the secret.
+    ///
+    /// Returns `None` if the shares found do not yet meet the SSKR
+    /// thresholds, so more depos can be asked before trying again. Fails if
+    /// any share found is too short to be an SSKR share, or is not the share
+    /// planned for its receipt.
+    pub fn recover<'a>(
+        &self,
+        results: impl IntoIterator<Item = &'a GetSharesResult>,
+    ) -> Result<Option<SSKRSecret>> {
+        let results = results.into_iter().collect::<Vec<_>>();
+        let shares = self
+            .shares
+            .iter()
+            .filter_map(|planned| {
+                results
+                    .iter()
+                    .find_map(|result| {
+                        result.data_for_receipt(&planned.receipt)
+                    })
+                    .map(|data| planned.parse(data.data()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        match threshold_shares(shares) {
+            Some(shares) => Ok(Some(sskr_combine(&shares)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves shares through the client for each depo, given in the
+    /// order of the endpoints, until there are enough to recover the
+    /// secret.
+    ///
+    /// `user_id` is the XID the shares were stored by, which their receipts
+    /// are bound to. A depo that fails to return its shares, or returns any
+    /// share that does not match its receipt or plan, is passed over, since
+    /// the point of spreading shares is to survive the loss of some of the
+    /// depos. Fails before asking any depo if any client sends to a depo
+    /// other than the one at its endpoint.
+    pub fn retrieve(
+        &self,
+        user_id: &XID,
+        clients: &[DepoClient],
+    ) -> Result<Option<SSKRSecret>> {
+        check_clients(&self.endpoints, clients)?;
+        let mut results = Vec::new();
+        for (endpoint, client) in clients.iter().enumerate() {
+            let request = self.get_shares_request(endpoint);
+            if request.receipts().is_empty() {
+                continue;
+            }
+            let Ok(result) = client.send(request.clone()) else {
+                continue;
+            };
+            let verification = result.verify(user_id, &request);
+            if !verification.mismatched().is_empty()
+                || !verification.unexpected().is_empty()
+            {
+                continue;
+            }
+            let well_formed = self
+                .shares
+                .iter()
+                .filter(|planned| planned.endpoint == endpoint)
+                .all(|planned| {
+                    result
+                        .data_for_receipt(&planned.receipt)
+                        .is_none_or(|data| planned.parse(data.data()).is_ok())
+                });
+            if !well_formed {
+                continue;
+            }
+            results.push(result);
+            if let Some(secret) = self.recover(&results)? {
+                return Ok(Some(secret));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Selects just enough of `shares` to meet their group and member
+/// thresholds, or returns `None` if they fall short.
+fn threshold_shares(shares: Vec<SSKRShare>) -> Option<Vec<SSKRShare>> {
+    let group_threshold = shares.first()?.group_threshold();
+    let mut groups: HashMap<usize, HashMap<usize, SSKRShare>> = HashMap::new();
+    for share in shares {
+        groups
+            .entry(share.group_index())
+            .or_default()
+            .insert(share.member_index(), share);
+    }
+    let selected = groups
+        .into_values()
+        .filter_map(|members| {
+            let member_threshold = members.values().next()?.member_threshold();
+            (members.len() >= member_threshold).then(|| {
+                members.into_values().take(member_threshold).collect()
+            })
+        })
+        .take(group_threshold)
+        .collect::<Vec<Vec<SSKRShare>>>();
+    if selected.len() < group_threshold {
+        return None;
+    }
+    Some(selected.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::{PrivateKeyBase, PublicKeysProvider, SSKRGroupSpec};
+    use bc_envelope::prelude::*;
+
+    use super::*;
+
+    fn endpoints(count: usize) -> Vec<DepoEndpoint> {
+        (0..count)
+            .map(|i| {
+                DepoEndpoint::new(
+                    format!("https://depo{}.example.com", i),
+                    PrivateKeyBase::new().public_keys().into(),
+                )
+            })
+            .collect()
+    }
+
+    fn user_id() -> XID {
+        XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap()
+    }
+
+    /// Stands in for each depo storing its shares, returning the results
+    /// it would give for `getShares`.
+    fn store(
+        distribution: &ShareDistribution,
+    ) -> (RecoveryPlan, Vec<GetSharesResult>) {
+        let mut receipts = Vec::new();
+        let mut results = Vec::new();
+        for endpoint in 0..distribution.endpoints().len() {
+            let request = distribution.store_request(endpoint);
+            let stored = request
+                .shares()
+                .iter()
+                .map(|share| {
+                    let receipt = Receipt::new(user_id(), share.data());
+                    (receipt, ByteString::from(share.data()))
+                })
+                .collect::<Vec<_>>();
+            receipts.push(stored.iter().map(|(r, _)| r.clone()).collect());
+            results.push(GetSharesResult::new(stored.into_iter().collect()));
+        }
+        (distribution.recovery_plan(receipts).unwrap(), results)
+    }
+
+    #[test]
+    fn test_single_group() {
+        let secret = SSKRSecret::new(b"my secret belongs to me.").unwrap();
+        let group = SSKRGroupSpec::new(2, 3).unwrap();
+        let spec = SSKRSpec::new(1, vec![group]).unwrap();
+        let distribution =
+            ShareDistribution::new(&secret, &spec, endpoints(3)).unwrap();
+        for endpoint in 0..3 {
+            assert_eq!(distribution.shares_for(endpoint).len(), 1);
+            let request = distribution.store_request(endpoint);
+            assert_eq!(
+                request.shares()[0].metadata().content_type(),
+                Some(SSKR_CONTENT_TYPE)
+            );
+        }
+
+        let (plan, results) = store(&distribution);
+        assert_eq!(plan.shares().len(), 3);
+        assert_eq!(plan.get_shares_request(1).receipts().len(), 1);
+
+        // One depo alone is not enough.
+        assert_eq!(plan.recover(&results[..1]).unwrap(), None);
+
+        // Any two are.
+        let recovered = plan.recover(&results[1..]).unwrap().unwrap();
+        assert_eq!(recovered, secret);
+        let recovered = plan.recover([&results[0], &results[2]]).unwrap();
+        assert_eq!(recovered, Some(secret));
+    }
+
+    #[test]
+    fn test_groups() {
+        let secret = SSKRSecret::new(b"my secret belongs to me.").unwrap();
+        let groups = vec![
+            SSKRGroupSpec::new(2, 3).unwrap(),
+            SSKRGroupSpec::new(1, 1).unwrap(),
+        ];
+        let spec = SSKRSpec::new(2, groups).unwrap();
+        let distribution =
+            ShareDistribution::new(&secret, &spec, endpoints(2)).unwrap();
+        assert_eq!(distribution.shares_for(0).len(), 2);
+        assert_eq!(distribution.shares_for(1).len(), 2);
+
+        let (plan, results) = store(&distribution);
+        let recovered = plan.recover(&results).unwrap();
+        assert_eq!(recovered, Some(secret));
+        assert_eq!(plan.recover(&results[1..]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_malformed_shares() {
+        let secret = SSKRSecret::new(b"my secret belongs to me.").unwrap();
+        let group = SSKRGroupSpec::new(2, 3).unwrap();
+        let spec = SSKRSpec::new(1, vec![group]).unwrap();
+        let distribution =
+            ShareDistribution::new(&secret, &spec, endpoints(3)).unwrap();
+        let (plan, results) = store(&distribution);
+
+        // A depo that returns short data gets an error, not a panic.
+        let receipt = plan.shares()[0].receipt().clone();
+        let short = GetSharesResult::new(
+            vec![(receipt, ByteString::from(b"abc"))].into_iter().collect(),
+        );
+        assert!(matches!(
+            plan.recover([&short, &results[1]]),
+            Err(Error::SSKRProcessing(SSKRError::ShareLengthInvalid))
+        ));
+
+        // So does one that returns another depo's share for its receipt.
+        let receipt = plan.shares()[0].receipt().clone();
+        let other = results[1].receipt_to_data().values().next().unwrap();
+        let swapped = GetSharesResult::new(
+            vec![(receipt, other.clone())].into_iter().collect(),
+        );
+        assert!(matches!(
+            plan.recover([&swapped, &results[2]]),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_distribution() {
+        let secret = SSKRSecret::new(b"my secret belongs to me.").unwrap();
+        let group = SSKRGroupSpec::new(2, 3).unwrap();
+        let spec = SSKRSpec::new(1, vec![group]).unwrap();
+        assert!(matches!(
+            ShareDistribution::new(&secret, &spec, vec![]),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+
+        // With two depos for three shares, one depo would hold two.
+        assert!(matches!(
+            ShareDistribution::new(&secret, &spec, endpoints(2)),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+        assert!(matches!(
+            ShareDistribution::new(&secret, &spec, endpoints(1)),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+
+        let distribution =
+            ShareDistribution::new(&secret, &spec, endpoints(3)).unwrap();
+        assert!(matches!(
+            distribution.recovery_plan(vec![vec![], vec![], vec![]]),
+            Err(Error::InvalidShareDistribution { .. })
+        ));
+    }
+}
@@ -0,0 +1,394 @@
+use bc_components::{Compressed, Salt};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+pub const LABEL_PREDICATE: &str = "label";
+pub const CONTENT_TYPE_PREDICATE: &str = "contentType";
+pub const APPLICATION_TAG_PREDICATE: &str = "applicationTag";
+pub const STORED_AT_PREDICATE: &str = "storedAt";
+pub const SIZE_PREDICATE: &str = "size";
+pub const SALT_PREDICATE: &str = "salt";
+
+/// The most bytes compressed share data may decompress to.
+pub const MAX_DECOMPRESSED_SHARE_SIZE: usize = 1024 * 1024;
+
+/// Descriptive information about a share, held by the depo alongside it.
+///
+/// The label, content type and application tag are supplied by the client
+/// when it stores the share, and are opaque to the depo. The time the share
+/// was stored and its size in bytes are supplied by the depo when it returns
+/// the share. Every field is optional, and each is encoded as an assertion
+/// on the envelope it describes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShareMetadata {
+    label: Option<String>,
+    content_type: Option<String>,
+    application_tag: Option<String>,
+    stored_at: Option<Date>,
+    size: Option<usize>,
+    salt: Option<Salt>,
+}
+
+impl ShareMetadata {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_content_type(
+        mut self,
+        content_type: impl Into<String>,
+    ) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn with_application_tag(
+        mut self,
+        application_tag: impl Into<String>,
+    ) -> Self {
+        self.application_tag = Some(application_tag.into());
+        self
+    }
+
+    pub fn with_stored_at(mut self, stored_at: Date) -> Self {
+        self.stored_at = Some(stored_at);
+        self
+    }
+
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_salt(mut self, salt: Salt) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// A human-readable name for the share, such as its SSKR group.
+    pub fn label(&self) -> Option<&str> { self.label.as_deref() }
+
+    /// The media type of the share's data.
+    pub fn content_type(&self) -> Option<&str> { self.content_type.as_deref() }
+
+    /// An application-defined tag, such as the backup the share belongs to.
+    pub fn application_tag(&self) -> Option<&str> {
+        self.application_tag.as_deref()
+    }
+
+    /// When the depo stored the share.
+    pub fn stored_at(&self) -> Option<&Date> { self.stored_at.as_ref() }
+
+    /// The size of the share's data in bytes.
+    pub fn size(&self) -> Option<usize> { self.size }
+
+    /// The salt mixed into the share's receipt, if the receipt is salted.
+    pub fn salt(&self) -> Option<&Salt> { self.salt.as_ref() }
+
+    /// Returns only the fields supplied by the client.
+    pub fn client_fields(&self) -> Self {
+        Self {
+            label: self.label.clone(),
+            content_type: self.content_type.clone(),
+            application_tag: self.application_tag.clone(),
+            stored_at: None,
+            size: None,
+            salt: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self == &Self::default() }
+
+    /// Adds an assertion to `envelope` for each field that is present.
+    pub fn add_to_envelope(&self, envelope: Envelope) -> Envelope {
+        envelope
+            .add_optional_assertion(LABEL_PREDICATE, self.label.clone())
+            .add_optional_assertion(
+                CONTENT_TYPE_PREDICATE,
+                self.content_type.clone(),
+            )
+            .add_optional_assertion(
+                APPLICATION_TAG_PREDICATE,
+                self.application_tag.clone(),
+            )
+            .add_optional_assertion(STORED_AT_PREDICATE, self.stored_at)
+            .add_optional_assertion(SIZE_PREDICATE, self.size)
+            .add_optional_assertion(SALT_PREDICATE, self.salt.clone())
+    }
+
+    /// Reads the fields asserted on `envelope`, ignoring any other
+    /// assertions.
+    pub fn from_envelope(envelope: &Envelope) -> Result<Self> {
+        let invalid = |e: bc_envelope::Error| Error::InvalidEnvelope {
+            message: format!("invalid share metadata: {}", e),
+        };
+        Ok(Self {
+            label: envelope
+                .extract_optional_object_for_predicate(LABEL_PREDICATE)
+                .map_err(invalid)?,
+            content_type: envelope
+                .extract_optional_object_for_predicate(CONTENT_TYPE_PREDICATE)
+                .map_err(invalid)?,
+            application_tag: envelope
+                .extract_optional_object_for_predicate(
+                    APPLICATION_TAG_PREDICATE,
+                )
+                .map_err(invalid)?,
+            stored_at: envelope
+                .extract_optional_object_for_predicate(STORED_AT_PREDICATE)
+                .map_err(invalid)?,
+            size: envelope
+                .extract_optional_object_for_predicate(SIZE_PREDICATE)
+                .map_err(invalid)?,
+            salt: envelope
+                .extract_optional_object_for_predicate(SALT_PREDICATE)
+                .map_err(invalid)?,
+        })
+    }
+}
+
+/// Encodes share data as an envelope, compressing it if `compressed`.
+pub fn share_data_envelope(data: ByteString, compressed: bool) -> Envelope {
+    let envelope = Envelope::new(data);
+    if compressed {
+        envelope.compress_subject().unwrap_or(envelope)
+    } else {
+        envelope
+    }
+}
+
+/// Checks that `compressed` decompresses to no more than
+/// `MAX_DECOMPRESSED_SHARE_SIZE` bytes without decompressing any more than
+/// that, so a small request cannot make the depo allocate without bound.
+///
+/// Both the size `compressed` declares and the size it actually inflates to
+/// are checked, as the declared size is only confirmed by the checksum once
+/// the data is decompressed.
+fn check_decompressed_size(compressed: &Compressed) -> Result<()> {
+    let too_large =
+        || Error::ShareTooLarge { max: MAX_DECOMPRESSED_SHARE_SIZE };
+    let invalid = |message: &str| Error::InvalidEnvelope {
+        message: format!("invalid compressed share data: {}", message),
+    };
+    // [checksum, decompressed_size, compressed_data, digest?]
+    let fields = compressed.untagged_cbor().try_into_array()?;
+    if fields.len() < 3 {
+        return Err(invalid("too few fields"));
+    }
+    let declared_size: usize = fields[1].clone().try_into()?;
+    if declared_size > MAX_DECOMPRESSED_SHARE_SIZE {
+        return Err(too_large());
+    }
+    let compressed_data = fields[2].clone().try_into_byte_string()?;
+    if compressed_data.len() >= declared_size {
+        // Stored uncompressed.
+        return Ok(());
+    }
+    miniz_oxide::inflate::decompress_to_vec_with_limit(
+        &compressed_data,
+        MAX_DECOMPRESSED_SHARE_SIZE,
+    )
+    .map_err(|e| match e.status {
+        miniz_oxide::inflate::TINFLStatus::HasMoreOutput => too_large(),
+        _ => invalid("corrupt data"),
+    })?;
+    Ok(())
+}
+
+/// Extracts share data from the subject of `envelope`, decompressing it if
+/// needed.
+///
+/// Fails with `Error::ShareTooLarge` if the data would decompress to more
+/// than `MAX_DECOMPRESSED_SHARE_SIZE` bytes.
+pub fn extract_share_data(envelope: Envelope) -> Result<ByteString> {
+    let envelope = if envelope.subject().is_compressed() {
+        if let EnvelopeCase::Compressed(compressed) = envelope.subject().case()
+        {
+            check_decompressed_size(compressed)?;
+        }
+        envelope.decompress_subject()?
+    } else {
+        envelope
+    };
+    envelope
+        .extract_subject()
+        .map_err(|e| Error::InvalidEnvelope {
+            message: format!("failed to extract share data: {}", e),
+        })
+}
+
+/// A share's data together with its metadata, as returned by `getShares`.
+///
+/// Encoded as the data with the metadata as assertions, so peers that only
+/// understand the data can still read it. The data may be compressed, in
+/// which case it is decompressed when decoded:
+///
+/// ```text
+/// Bytes(6) [
+///     "label": "Group 1"
+///     "size": 6
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredShare {
+    data: ByteString,
+    metadata: ShareMetadata,
+    compressed: bool,
+}
+
+impl StoredShare {
+    pub fn new(data: impl Into<ByteString>) -> Self {
+        Self::new_with_metadata(data, ShareMetadata::new())
+    }
+
+    pub fn new_with_metadata(
+        data: impl Into<ByteString>,
+        metadata: ShareMetadata,
+    ) -> Self {
+        Self { data: data.into(), metadata, compressed: false }
+    }
+
+    /// Encodes the data compressed.
+    pub fn with_compression(mut self) -> Self {
+        self.compressed = true;
+        self
+    }
+
+    /// The uncompressed data.
+    pub fn data(&self) -> &ByteString { &self.data }
+
+    pub fn metadata(&self) -> &ShareMetadata { &self.metadata }
+
+    pub fn is_compressed(&self) -> bool { self.compressed }
+}
+
+impl From<StoredShare> for Envelope {
+    fn from(value: StoredShare) -> Self {
+        value
+            .metadata
+            .add_to_envelope(share_data_envelope(value.data, value.compressed))
+    }
+}
+
+impl TryFrom<Envelope> for StoredShare {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let compressed = envelope.subject().is_compressed();
+        let metadata = ShareMetadata::from_envelope(&envelope)?;
+        let data = extract_share_data(envelope)?;
+        let share = Self::new_with_metadata(data, metadata);
+        Ok(if compressed { share.with_compression() } else { share })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_stored_share() {
+        bc_envelope::register_tags();
+
+        let metadata = ShareMetadata::new()
+            .with_label("Group 1")
+            .with_content_type("application/x-sskr")
+            .with_application_tag("wallet")
+            .with_size(6);
+        let share = StoredShare::new_with_metadata(b"data_1", metadata);
+        let envelope = share.to_envelope();
+        // println!("{}", envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(envelope.format(), indoc! {r#"
+            Bytes(6) [
+                "applicationTag": "wallet"
+                "contentType": "application/x-sskr"
+                "label": "Group 1"
+                "size": 6
+            ]
+        "#}.trim());
+        let decoded = StoredShare::try_from(envelope).unwrap();
+        assert_eq!(share, decoded);
+
+        let metadata = ShareMetadata::new()
+            .with_stored_at(Date::from_timestamp(1_000_000.0))
+            .with_size(6)
+            .with_salt(Salt::from_data(b"saltsalt"));
+        let share = StoredShare::new_with_metadata(b"data_1", metadata);
+        let decoded = StoredShare::try_from(share.to_envelope()).unwrap();
+        assert_eq!(share, decoded);
+
+        let data = "descriptor ".repeat(100).into_bytes();
+        let share = StoredShare::new_with_metadata(
+            data.clone(),
+            ShareMetadata::new().with_size(data.len()),
+        )
+        .with_compression();
+        let envelope = share.to_envelope();
+        // println!("{}", envelope.format());
+        #[rustfmt::skip]
+        assert_eq!(envelope.format(), indoc! {r#"
+            COMPRESSED [
+                "size": 1100
+            ]
+        "#}.trim());
+        let decoded = StoredShare::try_from(envelope).unwrap();
+        assert_eq!(share, decoded);
+        assert_eq!(decoded.data().data(), data.as_slice());
+
+        // A bare share, as older depos return it, has no metadata.
+        let decoded =
+            StoredShare::try_from(Envelope::new(ByteString::from(b"data_1")))
+                .unwrap();
+        assert!(decoded.metadata().is_empty());
+        assert_eq!(decoded.data().data(), b"data_1");
+    }
+
+    #[test]
+    fn test_client_fields() {
+        let metadata = ShareMetadata::new()
+            .with_label("Group 1")
+            .with_stored_at(Date::from_timestamp(1_000_000.0))
+            .with_size(6)
+            .with_salt(Salt::from_data(b"saltsalt"));
+        assert_eq!(
+            metadata.client_fields(),
+            ShareMetadata::new().with_label("Group 1")
+        );
+    }
+
+    #[test]
+    fn test_decompression_limit() {
+        bc_envelope::register_tags();
+
+        let data = vec![0u8; MAX_DECOMPRESSED_SHARE_SIZE / 2];
+        let envelope = share_data_envelope(data.clone().into(), true);
+        assert_eq!(extract_share_data(envelope).unwrap().data(), data);
+
+        let data = vec![0u8; MAX_DECOMPRESSED_SHARE_SIZE + 1];
+        let envelope = share_data_envelope(data.into(), true);
+        assert!(matches!(
+            extract_share_data(envelope),
+            Err(Error::ShareTooLarge { .. })
+        ));
+
+        // Data that declares a small size but inflates to more than the
+        // limit is refused before it is decompressed in full.
+        let bomb = miniz_oxide::deflate::compress_to_vec(
+            &vec![0u8; MAX_DECOMPRESSED_SHARE_SIZE * 2],
+            6,
+        );
+        let compressed =
+            Compressed::new(0, bomb.len() + 1, bomb, None).unwrap();
+        assert!(matches!(
+            check_decompressed_size(&compressed),
+            Err(Error::ShareTooLarge { .. })
+        ));
+    }
+}
@@ -0,0 +1,300 @@
+use bc_envelope::prelude::*;
+
+use crate::{
+    DeleteAccount, DeleteShares, DepoRequest, Error, FinishRecovery,
+    GetRecovery, GetRecoveryResult, GetShares, GetSharesResult, GetXIDDocument,
+    GetXIDDocumentResult, ListShares, ListSharesResult, OkResult, Receipt,
+    Result, SealedRecoveryManifest, StartRecovery, StartRecoveryResult,
+    StoreShare, StoreShareResult, StoreShares, StoreSharesResult,
+    UpdateRecovery, UpdateXIDDocument,
+};
+
+// CBOR tags for the depo's own types, taken from the "First Come First
+// Served" range. Each tag's name is the type of the UR that carries it.
+
+const_cbor_tag!(40700, DEPO_RECEIPT, "depo-receipt");
+const_cbor_tag!(40701, DEPO_RECOVERY_MANIFEST, "depo-recovery-manifest");
+
+const_cbor_tag!(40710, DEPO_REQUEST, "depo-request");
+const_cbor_tag!(40711, DEPO_DELETE_ACCOUNT, "depo-delete-account");
+const_cbor_tag!(40712, DEPO_DELETE_SHARES, "depo-delete-shares");
+const_cbor_tag!(40713, DEPO_FINISH_RECOVERY, "depo-finish-recovery");
+const_cbor_tag!(40714, DEPO_GET_RECOVERY, "depo-get-recovery");
+const_cbor_tag!(40715, DEPO_GET_SHARES, "depo-get-shares");
+const_cbor_tag!(40716, DEPO_GET_XID_DOCUMENT, "depo-get-xid-document");
+const_cbor_tag!(40717, DEPO_LIST_SHARES, "depo-list-shares");
+const_cbor_tag!(40718, DEPO_START_RECOVERY, "depo-start-recovery");
+const_cbor_tag!(40719, DEPO_STORE_SHARE, "depo-store-share");
+const_cbor_tag!(40720, DEPO_STORE_SHARES, "depo-store-shares");
+const_cbor_tag!(40721, DEPO_UPDATE_RECOVERY, "depo-update-recovery");
+const_cbor_tag!(40722, DEPO_UPDATE_XID_DOCUMENT, "depo-update-xid-document");
+
+const_cbor_tag!(40730, DEPO_GET_RECOVERY_RESULT, "depo-get-recovery-result");
+const_cbor_tag!(40731, DEPO_GET_SHARES_RESULT, "depo-get-shares-result");
+const_cbor_tag!(
+    40732,
+    DEPO_GET_XID_DOCUMENT_RESULT,
+    "depo-get-xid-document-result"
+);
+const_cbor_tag!(40733, DEPO_LIST_SHARES_RESULT, "depo-list-shares-result");
+const_cbor_tag!(40734, DEPO_OK_RESULT, "depo-ok-result");
+const_cbor_tag!(
+    40735,
+    DEPO_START_RECOVERY_RESULT,
+    "depo-start-recovery-result"
+);
+const_cbor_tag!(40736, DEPO_STORE_SHARE_RESULT, "depo-store-share-result");
+const_cbor_tag!(40737, DEPO_STORE_SHARES_RESULT, "depo-store-shares-result");
+
+/// Registers the depo's tags, along with those of Gordian Envelope, in
+/// `context`.
+pub fn register_tags_in(context: &mut FormatContext) {
+    bc_envelope::register_tags_in(context);
+    context.tags_mut().insert_all(vec![
+        cbor_tag!(DEPO_RECEIPT),
+        cbor_tag!(DEPO_RECOVERY_MANIFEST),
+        cbor_tag!(DEPO_REQUEST),
+        cbor_tag!(DEPO_DELETE_ACCOUNT),
+        cbor_tag!(DEPO_DELETE_SHARES),
+        cbor_tag!(DEPO_FINISH_RECOVERY),
+        cbor_tag!(DEPO_GET_RECOVERY),
+        cbor_tag!(DEPO_GET_SHARES),
+        cbor_tag!(DEPO_GET_XID_DOCUMENT),
+        cbor_tag!(DEPO_LIST_SHARES),
+        cbor_tag!(DEPO_START_RECOVERY),
+        cbor_tag!(DEPO_STORE_SHARE),
+        cbor_tag!(DEPO_STORE_SHARES),
+        cbor_tag!(DEPO_UPDATE_RECOVERY),
+        cbor_tag!(DEPO_UPDATE_XID_DOCUMENT),
+        cbor_tag!(DEPO_GET_RECOVERY_RESULT),
+        cbor_tag!(DEPO_GET_SHARES_RESULT),
+        cbor_tag!(DEPO_GET_XID_DOCUMENT_RESULT),
+        cbor_tag!(DEPO_LIST_SHARES_RESULT),
+        cbor_tag!(DEPO_OK_RESULT),
+        cbor_tag!(DEPO_START_RECOVERY_RESULT),
+        cbor_tag!(DEPO_STORE_SHARE_RESULT),
+        cbor_tag!(DEPO_STORE_SHARES_RESULT),
+    ]);
+}
+
+/// Registers the depo's tags, along with those of Gordian Envelope, in the
+/// global format context.
+pub fn register_tags() {
+    bc_envelope::with_format_context_mut!(|context: &mut FormatContext| {
+        register_tags_in(context);
+    });
+}
+
+/// Splits `value`'s UR into fragments of at most `max_fragment_len` bytes,
+/// for an animated QR.
+///
+/// Returns the minimal set of parts, followed by `extra_parts` fountain
+/// parts that let a scanner recover from missed frames.
+pub fn to_ur_parts(
+    value: &impl UREncodable,
+    max_fragment_len: usize,
+    extra_parts: usize,
+) -> Result<Vec<String>> {
+    let ur = value.ur();
+    let mut encoder = MultipartEncoder::new(&ur, max_fragment_len)?;
+    let count = encoder.parts_count() + extra_parts;
+    (0..count)
+        .map(|_| Ok(encoder.next_part()?))
+        .collect::<Result<Vec<_>>>()
+}
+
+/// Reassembles a value from multipart UR strings, in any order, which may
+/// include duplicates and fountain parts.
+pub fn from_ur_parts<T, I, S>(parts: I) -> Result<T>
+where
+    T: URDecodable,
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut decoder = MultipartDecoder::new();
+    for part in parts {
+        decoder.receive(part.as_ref())?;
+        if decoder.is_complete() {
+            break;
+        }
+    }
+    match decoder.message()? {
+        Some(ur) => Ok(T::from_ur(&ur)?),
+        None => Err(Error::IncompleteUR),
+    }
+}
+
+/// Implements the tagged CBOR traits, and so bc-ur's `UREncodable` and
+/// `URDecodable`, for a type whose untagged CBOR is that of its envelope.
+/// `$to_envelope` and `$from_envelope` convert between the two.
+macro_rules! impl_tagged_cbor {
+    ($type:ty, $tag:ident, $to_envelope:expr, $from_envelope:expr) => {
+        impl CBORTagged for $type {
+            fn cbor_tags() -> Vec<Tag> { vec![cbor_tag!($tag)] }
+        }
+
+        impl From<$type> for CBOR {
+            fn from(value: $type) -> Self { value.tagged_cbor() }
+        }
+
+        impl CBORTaggedEncodable for $type {
+            fn untagged_cbor(&self) -> CBOR {
+                let to_envelope: fn(&$type) -> Envelope = $to_envelope;
+                to_envelope(self).untagged_cbor()
+            }
+        }
+
+        impl TryFrom<CBOR> for $type {
+            type Error = dcbor::Error;
+
+            fn try_from(cbor: CBOR) -> dcbor::Result<Self> {
+                Self::from_tagged_cbor(cbor)
+            }
+        }
+
+        impl CBORTaggedDecodable for $type {
+            fn from_untagged_cbor(cbor: CBOR) -> dcbor::Result<Self> {
+                let from_envelope: fn(Envelope) -> Result<$type> =
+                    $from_envelope;
+                from_envelope(Envelope::from_untagged_cbor(cbor)?)
+                    .map_err(|error| dcbor::Error::msg(error.to_string()))
+            }
+        }
+    };
+}
+
+/// Implements the tagged CBOR traits for a type encoded directly as an
+/// envelope.
+macro_rules! impl_envelope_ur {
+    ($type:ty, $tag:ident) => {
+        impl_tagged_cbor!(
+            $type,
+            $tag,
+            |value| value.clone().into(),
+            |envelope| <$type>::try_from(envelope)
+        );
+    };
+}
+
+/// Implements the tagged CBOR traits for a request, which is encoded as an
+/// expression.
+macro_rules! impl_expression_ur {
+    ($type:ty, $tag:ident) => {
+        impl_tagged_cbor!(
+            $type,
+            $tag,
+            |value| Expression::from(value.clone()).into_envelope(),
+            |envelope| <$type>::try_from(Expression::try_from(envelope)?)
+        );
+    };
+}
+
+impl_envelope_ur!(Receipt, DEPO_RECEIPT);
+impl_envelope_ur!(SealedRecoveryManifest, DEPO_RECOVERY_MANIFEST);
+
+impl_expression_ur!(DepoRequest, DEPO_REQUEST);
+impl_expression_ur!(DeleteAccount, DEPO_DELETE_ACCOUNT);
+impl_expression_ur!(DeleteShares, DEPO_DELETE_SHARES);
+impl_expression_ur!(FinishRecovery, DEPO_FINISH_RECOVERY);
+impl_expression_ur!(GetRecovery, DEPO_GET_RECOVERY);
+impl_expression_ur!(GetShares, DEPO_GET_SHARES);
+impl_expression_ur!(GetXIDDocument, DEPO_GET_XID_DOCUMENT);
+impl_expression_ur!(ListShares, DEPO_LIST_SHARES);
+impl_expression_ur!(StartRecovery, DEPO_START_RECOVERY);
+impl_expression_ur!(StoreShare, DEPO_STORE_SHARE);
+impl_expression_ur!(StoreShares, DEPO_STORE_SHARES);
+impl_expression_ur!(UpdateRecovery, DEPO_UPDATE_RECOVERY);
+impl_expression_ur!(UpdateXIDDocument, DEPO_UPDATE_XID_DOCUMENT);
+
+impl_envelope_ur!(GetRecoveryResult, DEPO_GET_RECOVERY_RESULT);
+impl_envelope_ur!(GetSharesResult, DEPO_GET_SHARES_RESULT);
+impl_envelope_ur!(GetXIDDocumentResult, DEPO_GET_XID_DOCUMENT_RESULT);
+impl_envelope_ur!(ListSharesResult, DEPO_LIST_SHARES_RESULT);
+impl_envelope_ur!(OkResult, DEPO_OK_RESULT);
+impl_envelope_ur!(StartRecoveryResult, DEPO_START_RECOVERY_RESULT);
+impl_envelope_ur!(StoreShareResult, DEPO_STORE_SHARE_RESULT);
+impl_envelope_ur!(StoreSharesResult, DEPO_STORE_SHARES_RESULT);
+
+#[cfg(test)]
+mod tests {
+    use bc_components::XID;
+
+    use super::*;
+
+    fn user_id() -> XID {
+        XID::from_data_ref(hex_literal::hex!(
+            "8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_receipt_ur() {
+        register_tags();
+
+        let receipt = Receipt::new(user_id(), b"data");
+        let ur_string = receipt.ur_string();
+        assert!(ur_string.starts_with("ur:depo-receipt/"));
+        assert_eq!(Receipt::from_ur_string(&ur_string).unwrap(), receipt);
+
+        // A UR of another type is rejected.
+        let request = GetShares::new(vec![receipt.clone()]);
+        let ur_string = request.ur_string();
+        assert!(ur_string.starts_with("ur:depo-get-shares/"));
+        assert!(Receipt::from_ur_string(&ur_string).is_err());
+        assert_eq!(GetShares::from_ur_string(&ur_string).unwrap(), request);
+
+        // The tagged CBOR carries the registered tag.
+        let cbor = receipt.tagged_cbor();
+        assert_eq!(Receipt::try_from(cbor).unwrap(), receipt);
+        let name = with_format_context!(|context: &FormatContext| {
+            context.tags().name_for_value(TAG_DEPO_RECEIPT)
+        });
+        assert_eq!(name, TAG_NAME_DEPO_RECEIPT);
+    }
+
+    #[test]
+    fn test_request_ur() {
+        register_tags();
+
+        let request = StoreShare::new(b"data");
+        let decoded =
+            StoreShare::from_ur_string(request.ur_string()).unwrap();
+        assert_eq!(request, decoded);
+
+        let request = DepoRequest::StoreShare(request);
+        let decoded =
+            DepoRequest::from_ur_string(request.ur_string()).unwrap();
+        assert_eq!(request, decoded);
+
+        let result = StoreShareResult::new(Receipt::new(user_id(), b"data"));
+        let decoded =
+            StoreShareResult::from_ur_string(result.ur_string()).unwrap();
+        assert_eq!(result, decoded);
+    }
+
+    #[test]
+    fn test_multipart_ur() {
+        register_tags();
+
+        let request = StoreShare::new(vec![0x5a; 1000]);
+        let parts = to_ur_parts(&request, 100, 10).unwrap();
+        assert!(parts.len() > 10);
+        assert!(
+            parts
+                .iter()
+                .all(|part| part.starts_with("ur:depo-store-share/"))
+        );
+        let decoded: StoreShare = from_ur_parts(&parts).unwrap();
+        assert_eq!(decoded, request);
+
+        // Fountain parts stand in for a missed frame.
+        let decoded: StoreShare = from_ur_parts(&parts[1..]).unwrap();
+        assert_eq!(decoded, request);
+
+        assert!(matches!(
+            from_ur_parts::<StoreShare, _, _>(&parts[..2]),
+            Err(Error::IncompleteUR)
+        ));
+    }
+}